@@ -0,0 +1,25 @@
+use serde_derive::{Deserialize, Serialize};
+
+/// Which pre-compressed variants to write next to the generated files
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressOutput {
+    /// Write a `.gz` file next to each compressible file. `false` by default.
+    pub gzip: bool,
+    /// Write a `.br` file next to each compressible file. `false` by default.
+    pub brotli: bool,
+    /// Files smaller than that, in bytes, are not worth compressing. 1024 by default.
+    pub min_size: u64,
+}
+
+impl CompressOutput {
+    pub fn is_enabled(&self) -> bool {
+        self.gzip || self.brotli
+    }
+}
+
+impl Default for CompressOutput {
+    fn default() -> Self {
+        CompressOutput { gzip: false, brotli: false, min_size: 1024 }
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// The response headers to send for the paths matching `path`, like in a Netlify `_headers` file
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderRule {
+    /// The path the headers are for, which can contain `*` and `:placeholder` segments
+    #[serde(rename = "for")]
+    pub path: String,
+    /// Header name -> value
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+/// A redirect, like a line of a Netlify `_redirects` file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redirect {
+    /// The path to redirect, which can contain `*` and `:placeholder` segments
+    pub from: String,
+    /// Where to redirect, which can use the `:splat` and placeholders of `from`
+    pub to: String,
+    /// A 3xx status is a redirect, anything else serves `to` with that status. 301 by default.
+    #[serde(default = "default_status")]
+    pub status: u16,
+    /// Whether to redirect even if there is a page at `from`. `false` by default.
+    #[serde(default)]
+    pub force: bool,
+}
+
+fn default_status() -> u16 {
+    301
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 use errors::{bail, Result};
 use serde_derive::{Deserialize, Serialize};
@@ -7,7 +7,7 @@ use unic_langid::LanguageIdentifier;
 use crate::config::search;
 use crate::config::taxonomies;
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct LanguageOptions {
     /// Title of the site. Defaults to None
@@ -28,22 +28,7 @@ pub struct LanguageOptions {
     /// another `String` representing its translation.
     ///
     /// Use `get_translation()` method for translating key into different languages.
-    pub translations: HashMap<String, String>,
-}
-
-impl Default for LanguageOptions {
-    fn default() -> Self {
-        LanguageOptions {
-            title: None,
-            description: None,
-            generate_feed: false,
-            feed_filename: String::new(),
-            build_search_index: false,
-            taxonomies: Vec::new(),
-            search: search::Search::default(),
-            translations: HashMap::new(),
-        }
-    }
+    pub translations: BTreeMap<String, String>,
 }
 
 /// We want to ensure the language codes are valid ones
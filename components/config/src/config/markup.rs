@@ -12,6 +12,8 @@ use errors::{bail, Result};
 use crate::highlighting::{CLASS_STYLE, THEME_SET};
 
 pub const DEFAULT_HIGHLIGHT_THEME: &str = "base16-ocean-dark";
+/// The admonitions of GitHub, in the order of its documentation
+pub const DEFAULT_ADMONITION_TYPES: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(default)]
@@ -49,6 +51,27 @@ pub struct Markdown {
     pub external_links_no_referrer: bool,
     /// Whether smart punctuation is enabled (changing quotes, dashes, dots etc in their typographic form)
     pub smart_punctuation: bool,
+    /// Whether to render the blockquotes starting with `[!TYPE]` as admonitions
+    pub render_admonitions: bool,
+    /// The types of admonitions recognised, compared case-insensitively
+    pub admonition_types: Vec<String>,
+    /// Whether to render the `$...$` and `$$...$$` LaTeX formulas to MathML
+    pub math: bool,
+    /// Whether to render the `Term` lines followed by `: Definition` lines as definition lists
+    pub definition_lists: bool,
+    /// Whether to render `~text~` as subscript
+    pub subscript: bool,
+    /// Whether to render `^text^` as superscript
+    pub superscript: bool,
+    /// Whether to render `==text==` as highlighted with `<mark>`
+    pub mark: bool,
+    /// Whether to add the dimensions of the local images and `loading="lazy"` to them
+    pub images: bool,
+    /// The widths of the resized variants of the images to put in their `srcset`, when `images`
+    /// is enabled. Empty by default, for no `srcset`
+    pub image_widths: Vec<u32>,
+    /// The `sizes` attribute of the images having a `srcset`
+    pub image_sizes: Option<String>,
     /// A list of directories to search for additional `.sublime-syntax` and `.tmTheme` files in.
     pub extra_syntaxes_and_themes: Vec<String>,
     /// The compiled extra syntaxes into a syntax set
@@ -153,6 +176,16 @@ impl Markdown {
         Ok(())
     }
 
+    /// The admonition type of the `[!TYPE]` marker, if it is one of the configured ones
+    pub fn admonition_type(&self, marker: &str) -> Option<String> {
+        let kind = marker.strip_prefix("[!")?.strip_suffix(']')?.to_lowercase();
+        if self.admonition_types.iter().any(|t| t.to_lowercase() == kind) {
+            Some(kind)
+        } else {
+            None
+        }
+    }
+
     pub fn has_external_link_tweaks(&self) -> bool {
         self.external_links_target_blank
             || self.external_links_no_follow
@@ -196,6 +229,16 @@ impl Default for Markdown {
             external_links_no_follow: false,
             external_links_no_referrer: false,
             smart_punctuation: false,
+            render_admonitions: false,
+            admonition_types: DEFAULT_ADMONITION_TYPES.iter().map(|t| t.to_string()).collect(),
+            math: false,
+            definition_lists: false,
+            subscript: false,
+            superscript: false,
+            mark: false,
+            images: false,
+            image_widths: vec![],
+            image_sizes: None,
             extra_syntaxes_and_themes: vec![],
             extra_syntax_set: None,
             extra_theme_set: Arc::new(None),
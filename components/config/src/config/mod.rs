@@ -1,11 +1,15 @@
+pub mod compress;
+pub mod headers;
 pub mod languages;
 pub mod link_checker;
 pub mod markup;
+pub mod post_process;
 pub mod search;
+pub mod serve;
 pub mod slugify;
 pub mod taxonomies;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 use globset::{Glob, GlobSet, GlobSetBuilder};
@@ -44,7 +48,7 @@ pub struct Config {
     /// The list of supported languages outside of the default one
     pub languages: HashMap<String, languages::LanguageOptions>,
     /// The translations strings for the default language
-    translations: HashMap<String, String>,
+    translations: BTreeMap<String, String>,
 
     /// Whether to generate a feed. Defaults to false.
     pub generate_feed: bool,
@@ -62,6 +66,17 @@ pub struct Config {
     pub compile_sass: bool,
     /// Whether to minify the html output
     pub minify_html: bool,
+    /// The transforms to apply to every rendered HTML file, in order
+    pub post_process: Vec<post_process::PostProcess>,
+    /// The pre-compressed variants to write next to the generated files
+    pub compress_output: compress::CompressOutput,
+    /// The response headers sent by `zola serve`, added to the ones of `static/_headers`
+    pub headers: Vec<headers::HeaderRule>,
+    /// The redirects done by `zola serve`, added to the ones of `static/_redirects`
+    pub redirects: Vec<headers::Redirect>,
+    /// Whether to write the `headers` and `redirects` to the `_headers` and `_redirects` files
+    /// of the output directory. Defaults to false.
+    pub generate_headers_and_redirects: bool,
     /// Whether to build the search index for the content
     pub build_search_index: bool,
     /// A list of file glob patterns to ignore when processing the content folder. Defaults to none.
@@ -70,6 +85,11 @@ pub struct Config {
     pub ignored_content: Vec<String>,
     #[serde(skip_serializing, skip_deserializing)] // not a typo, 2 are needed
     pub ignored_content_globset: Option<GlobSet>,
+    /// A list of file glob patterns: the static files and compiled Sass files matching them are
+    /// written with a hash of their content in their name. Defaults to none.
+    pub fingerprint_assets: Vec<String>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub fingerprint_assets_globset: Option<GlobSet>,
 
     /// The mode Zola is currently being ran on. Some logging/feature can differ depending on the
     /// command being used.
@@ -85,6 +105,8 @@ pub struct Config {
     pub search: search::Search,
     /// The config for the Markdown rendering: syntax highlighting and everything
     pub markdown: markup::Markdown,
+    /// The options of `zola serve`
+    pub serve: serve::Serve,
     /// All user params set in [extra] in the config
     pub extra: HashMap<String, Toml>,
 }
@@ -95,13 +117,14 @@ pub struct SerializedConfig<'a> {
     mode: Mode,
     title: &'a Option<String>,
     description: &'a Option<String>,
-    languages: HashMap<&'a String, &'a languages::LanguageOptions>,
+    // Maps are sorted so the output doesn't change between builds
+    languages: BTreeMap<&'a String, &'a languages::LanguageOptions>,
     default_language: &'a str,
     generate_feed: bool,
     feed_filename: &'a str,
     taxonomies: &'a [taxonomies::Taxonomy],
     build_search_index: bool,
-    extra: &'a HashMap<String, Toml>,
+    extra: BTreeMap<&'a String, &'a Toml>,
 }
 
 impl Config {
@@ -125,23 +148,19 @@ impl Config {
 
         config.add_default_language();
 
-        if !config.ignored_content.is_empty() {
-            // Convert the file glob strings into a compiled glob set matcher. We want to do this once,
-            // at program initialization, rather than for every page, for example. We arrange for the
-            // globset matcher to always exist (even though it has to be an inside an Option at the
-            // moment because of the TOML serializer); if the glob set is empty the `is_match` function
-            // of the globber always returns false.
-            let mut glob_set_builder = GlobSetBuilder::new();
-            for pat in &config.ignored_content {
-                let glob = match Glob::new(pat) {
-                    Ok(g) => g,
-                    Err(e) => bail!("Invalid ignored_content glob pattern: {}, error = {}", pat, e),
-                };
-                glob_set_builder.add(glob);
-            }
-            config.ignored_content_globset =
-                Some(glob_set_builder.build().expect("Bad ignored_content in config file."));
+        for transform in &config.post_process {
+            transform.validate()?;
         }
+        config.serve.validate()?;
+
+        // Convert the file glob strings into a compiled glob set matcher. We want to do this once,
+        // at program initialization, rather than for every page, for example. We arrange for the
+        // globset matcher to always exist (even though it has to be an inside an Option at the
+        // moment because of the TOML serializer); if the glob set is empty the `is_match` function
+        // of the globber always returns false.
+        config.ignored_content_globset = build_globset("ignored_content", &config.ignored_content)?;
+        config.fingerprint_assets_globset =
+            build_globset("fingerprint_assets", &config.fingerprint_assets)?;
 
         Ok(config)
     }
@@ -297,11 +316,30 @@ impl Config {
             feed_filename: &options.feed_filename,
             taxonomies: &options.taxonomies,
             build_search_index: options.build_search_index,
-            extra: &self.extra,
+            extra: self.extra.iter().collect(),
         }
     }
 }
 
+/// Compiles the glob patterns of the config option `name`, `None` if there are none
+fn build_globset(name: &str, patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut glob_set_builder = GlobSetBuilder::new();
+    for pat in patterns {
+        let glob = match Glob::new(pat) {
+            Ok(g) => g,
+            Err(e) => bail!("Invalid {} glob pattern: {}, error = {}", name, pat, e),
+        };
+        glob_set_builder.add(glob);
+    }
+    match glob_set_builder.build() {
+        Ok(glob_set) => Ok(Some(glob_set)),
+        Err(e) => bail!("Bad {} in config file: {}", name, e),
+    }
+}
+
 // merge TOML data that can be a table, or anything else
 pub fn merge(into: &mut Toml, from: &Toml) -> Result<()> {
     match (from.is_table(), into.is_table()) {
@@ -346,16 +384,24 @@ impl Default for Config {
             taxonomies: Vec::new(),
             compile_sass: false,
             minify_html: false,
+            post_process: Vec::new(),
+            compress_output: compress::CompressOutput::default(),
+            headers: Vec::new(),
+            redirects: Vec::new(),
+            generate_headers_and_redirects: false,
             mode: Mode::Build,
             build_search_index: false,
             ignored_content: Vec::new(),
             ignored_content_globset: None,
-            translations: HashMap::new(),
+            fingerprint_assets: Vec::new(),
+            fingerprint_assets_globset: None,
+            translations: BTreeMap::new(),
             output_dir: "public".to_string(),
             link_checker: link_checker::LinkChecker::default(),
             slugify: slugify::Slugify::default(),
             search: search::Search::default(),
             markdown: markup::Markdown::default(),
+            serve: serve::Serve::default(),
             extra: HashMap::new(),
         }
     }
@@ -363,6 +409,7 @@ impl Default for Config {
 
 #[cfg(test)]
 mod tests {
+    use super::post_process::PostProcess;
     use super::*;
     use utils::slugs::SlugifyStrategy;
 
@@ -578,6 +625,85 @@ ignored_content = ["*.{graphml,iso}", "*.py?"]
         assert!(!g.is_match("foo.py"));
     }
 
+    #[test]
+    fn can_parse_fingerprint_assets() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+fingerprint_assets = ["*.css", "js/**"]
+        "#;
+
+        let config = Config::parse(config_str).unwrap();
+        let g = config.fingerprint_assets_globset.unwrap();
+        assert!(g.is_match("site.css"));
+        assert!(g.is_match("css/site.css"));
+        assert!(g.is_match("js/vendor/app.js"));
+        assert!(!g.is_match("img/logo.png"));
+
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+fingerprint_assets = ["[*.css"]
+        "#;
+        assert!(Config::parse(config_str).is_err());
+    }
+
+    #[test]
+    fn can_parse_headers_and_redirects() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+
+[[headers]]
+for = "/*"
+[headers.values]
+Content-Security-Policy = "default-src 'self'"
+
+[[redirects]]
+from = "/old/*"
+to = "/new/:splat"
+
+[[redirects]]
+from = "/app/*"
+to = "/app/index.html"
+status = 200
+force = true
+        "#;
+
+        let config = Config::parse(config_str).unwrap();
+        assert_eq!(config.headers.len(), 1);
+        assert_eq!(config.headers[0].path, "/*");
+        assert_eq!(config.headers[0].values["Content-Security-Policy"], "default-src 'self'");
+        assert_eq!(config.redirects.len(), 2);
+        assert_eq!(config.redirects[0].status, 301);
+        assert!(!config.redirects[0].force);
+        assert_eq!(config.redirects[1].status, 200);
+        assert!(config.redirects[1].force);
+        assert!(!config.generate_headers_and_redirects);
+    }
+
+    #[test]
+    fn can_parse_serve_proxy() {
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+
+[serve.proxy]
+"/api/" = "http://localhost:8080/"
+        "#;
+        let config = Config::parse(config_str).unwrap();
+        assert_eq!(config.serve.proxy["/api/"], "http://localhost:8080/");
+
+        let config_str = r#"
+title = "My site"
+base_url = "example.com"
+
+[serve.proxy]
+"/api/" = "https://example.com/"
+        "#;
+        assert!(Config::parse(config_str).is_err());
+    }
+
     #[test]
     fn link_checker_skip_anchor_prefixes() {
         let config_str = r#"
@@ -718,4 +844,55 @@ title = "Zola"
         let serialised = config.serialize(&config.default_language);
         assert_eq!(serialised.title, &config.title);
     }
+
+    #[test]
+    fn can_parse_post_process_transforms() {
+        let config = r#"
+base_url = "https://www.getzola.org/"
+
+[[post_process]]
+transform = "lazy_images"
+
+[[post_process]]
+transform = "preload"
+links = [{ href = "/fonts/inter.woff2", as = "font", type = "font/woff2", crossorigin = true }]
+
+[[post_process]]
+transform = "command"
+command = ["tidy", "-q"]
+    "#;
+
+        let config = Config::parse(config).unwrap();
+        assert_eq!(config.post_process.len(), 3);
+        assert_eq!(config.post_process[0], PostProcess::LazyImages);
+        match config.post_process[1] {
+            PostProcess::Preload { ref links } => {
+                assert_eq!(links[0].r#as, "font");
+                assert!(links[0].crossorigin);
+            }
+            _ => panic!("Expected a preload transform"),
+        }
+        assert_eq!(
+            config.post_process[2],
+            PostProcess::Command { command: vec!["tidy".to_string(), "-q".to_string()] }
+        );
+    }
+
+    #[test]
+    fn errors_on_invalid_post_process_transforms() {
+        let unknown = r#"
+base_url = "https://www.getzola.org/"
+[[post_process]]
+transform = "unknown"
+    "#;
+        assert!(Config::parse(unknown).is_err());
+
+        let empty_command = r#"
+base_url = "https://www.getzola.org/"
+[[post_process]]
+transform = "command"
+command = []
+    "#;
+        assert!(Config::parse(empty_command).is_err());
+    }
 }
@@ -0,0 +1,50 @@
+use serde_derive::{Deserialize, Serialize};
+
+use errors::{bail, Result};
+
+/// A transform applied to every rendered HTML file before it is written, in the order
+/// they are listed in the config
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "transform", rename_all = "snake_case")]
+pub enum PostProcess {
+    /// Adds `loading="lazy"` to the `<img>` tags that don't have a `loading` attribute
+    LazyImages,
+    /// Rewrites the links and sources pointing to the site base URL to be relative to the document
+    RelativeLinks,
+    /// Adds a `<link rel="preload">` at the end of the `<head>` for each link
+    Preload { links: Vec<PreloadLink> },
+    /// Pipes the document through an external command: it gets the HTML on stdin and
+    /// has to write the transformed HTML on stdout
+    Command { command: Vec<String> },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreloadLink {
+    pub href: String,
+    /// What kind of resource it is, eg `font`, `style` or `script`
+    pub r#as: String,
+    /// The MIME type of the resource, eg `font/woff2`
+    pub r#type: Option<String>,
+    /// Whether to add the `crossorigin` attribute, required for fonts
+    #[serde(default)]
+    pub crossorigin: bool,
+}
+
+impl PostProcess {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            PostProcess::Command { command } if command.is_empty() => {
+                bail!("The `command` of a `command` post-processing transform cannot be empty")
+            }
+            PostProcess::Preload { links } => {
+                for link in links {
+                    if link.href.is_empty() {
+                        bail!("A link to preload is missing its `href`");
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::collections::BTreeMap;
+
+use serde_derive::{Deserialize, Serialize};
+
+use errors::{bail, Result};
+
+/// The options only used by `zola serve`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Serve {
+    /// Path prefix -> URL of the server the requests under that prefix are forwarded to,
+    /// the prefix being replaced by the URL. Empty by default.
+    pub proxy: BTreeMap<String, String>,
+}
+
+impl Serve {
+    pub fn validate(&self) -> Result<()> {
+        for (prefix, upstream) in &self.proxy {
+            if !prefix.starts_with('/') {
+                bail!("The `serve.proxy` path prefix `{}` needs to start with a `/`", prefix);
+            }
+            // The dev server only talks plain HTTP to the upstream servers
+            if !upstream.starts_with("http://") {
+                bail!(
+                    "The `serve.proxy` URL for `{}` needs to start with `http://`, got `{}`",
+                    prefix,
+                    upstream
+                );
+            }
+        }
+        Ok(())
+    }
+}
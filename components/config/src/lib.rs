@@ -3,8 +3,17 @@ pub mod highlighting;
 mod theme;
 
 pub use crate::config::{
-    languages::LanguageOptions, link_checker::LinkChecker, search::Search, slugify::Slugify,
-    taxonomies::Taxonomy, Config,
+    compress::CompressOutput,
+    headers::{HeaderRule, Redirect},
+    languages::LanguageOptions,
+    link_checker::LinkChecker,
+    markup::Markdown,
+    post_process::{PostProcess, PreloadLink},
+    search::Search,
+    serve::Serve,
+    slugify::Slugify,
+    taxonomies::Taxonomy,
+    Config,
 };
 use errors::Result;
 
@@ -8,7 +8,7 @@ use std::path::Path;
 mod page;
 mod section;
 
-pub use page::PageFrontMatter;
+pub use page::{parse_datetime, parse_local_datetime, PageFrontMatter};
 pub use section::SectionFrontMatter;
 
 lazy_static! {
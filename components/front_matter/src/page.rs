@@ -37,6 +37,18 @@ pub struct PageFrontMatter {
     pub datetime_tuple: Option<(i32, u32, u32)>,
     /// Whether this page is a draft
     pub draft: bool,
+    /// The page is only loaded from that date on
+    #[serde(default, deserialize_with = "from_toml_datetime")]
+    pub publish_date: Option<String>,
+    /// Chrono converted publish date
+    #[serde(default, skip_deserializing)]
+    pub publish_datetime: Option<NaiveDateTime>,
+    /// The page is not loaded anymore from that date on
+    #[serde(default, deserialize_with = "from_toml_datetime")]
+    pub expiry_date: Option<String>,
+    /// Chrono converted expiry date
+    #[serde(default, skip_deserializing)]
+    pub expiry_datetime: Option<NaiveDateTime>,
     /// The page slug. Will be used instead of the filename if present
     /// Can't be an empty string if present
     pub slug: Option<String>,
@@ -68,7 +80,7 @@ pub struct PageFrontMatter {
 /// 2. a local datetime (RFC3339 with timezone omitted)
 /// 3. a local date (YYYY-MM-DD).
 /// This tries each in order.
-fn parse_datetime(d: &str) -> Option<NaiveDateTime> {
+pub fn parse_datetime(d: &str) -> Option<NaiveDateTime> {
     DateTime::parse_from_rfc3339(d)
         .or_else(|_| DateTime::parse_from_rfc3339(format!("{}Z", d).as_ref()))
         .map(|s| s.naive_local())
@@ -76,6 +88,16 @@ fn parse_datetime(d: &str) -> Option<NaiveDateTime> {
         .ok()
 }
 
+/// Parses a date in the same formats as `parse_datetime` but in the local time, to compare it
+/// with the current time: a date with an offset is converted to the local time while the other
+/// ones are taken as being in the local time already.
+pub fn parse_local_datetime(d: &str) -> Option<NaiveDateTime> {
+    match DateTime::parse_from_rfc3339(d) {
+        Ok(datetime) => Some(datetime.with_timezone(&Local).naive_local()),
+        Err(_) => parse_datetime(d),
+    }
+}
+
 impl PageFrontMatter {
     pub fn parse(raw: &RawFrontMatter) -> Result<PageFrontMatter> {
         let mut f: PageFrontMatter = raw.deserialize()?;
@@ -104,6 +126,21 @@ impl PageFrontMatter {
                 bail!("`date` could not be parsed: {}.", date);
             }
         }
+        if let Some(ref date) = f.publish_date {
+            if f.publish_datetime.is_none() {
+                bail!("`publish_date` could not be parsed: {}.", date);
+            }
+        }
+        if let Some(ref date) = f.expiry_date {
+            if f.expiry_datetime.is_none() {
+                bail!("`expiry_date` could not be parsed: {}.", date);
+            }
+        }
+        if let (Some(publish), Some(expiry)) = (f.publish_datetime, f.expiry_datetime) {
+            if expiry <= publish {
+                bail!("`expiry_date` needs to be after `publish_date`.");
+            }
+        }
 
         Ok(f)
     }
@@ -117,6 +154,16 @@ impl PageFrontMatter {
         self.updated_datetime = self.updated.as_ref().map(|s| s.as_ref()).and_then(parse_datetime);
         self.updated_datetime_tuple =
             self.updated_datetime.map(|dt| (dt.year(), dt.month(), dt.day()));
+
+        self.publish_datetime = self.publish_date.as_deref().and_then(parse_local_datetime);
+        self.expiry_datetime = self.expiry_date.as_deref().and_then(parse_local_datetime);
+    }
+
+    /// Whether the page is published at the given datetime according to its
+    /// `publish_date` and `expiry_date`
+    pub fn is_published_at(&self, datetime: NaiveDateTime) -> bool {
+        self.publish_datetime.map_or(true, |publish| publish <= datetime)
+            && self.expiry_datetime.map_or(true, |expiry| datetime < expiry)
     }
 
     pub fn weight(&self) -> usize {
@@ -136,6 +183,10 @@ impl Default for PageFrontMatter {
             datetime: None,
             datetime_tuple: None,
             draft: false,
+            publish_date: None,
+            publish_datetime: None,
+            expiry_date: None,
+            expiry_datetime: None,
             slug: None,
             path: None,
             taxonomies: HashMap::new(),
@@ -152,6 +203,7 @@ impl Default for PageFrontMatter {
 mod tests {
     use super::PageFrontMatter;
     use super::RawFrontMatter;
+    use chrono::{Local, NaiveDate, TimeZone, Utc};
     use tera::to_value;
     use test_case::test_case;
 
@@ -338,6 +390,57 @@ date: 2002-14-01
 
     #[test_case(&RawFrontMatter::Toml(r#"
 title = "Hello"
+publish_date = 2026-12-01
+expiry_date = 2027-01-01T12:00:00
+"#); "toml")]
+    #[test_case(&RawFrontMatter::Yaml(r#"
+title: Hello
+publish_date: 2026-12-01
+expiry_date: 2027-01-01T12:00:00
+"#); "yaml")]
+    fn can_parse_publish_and_expiry_dates(content: &RawFrontMatter) {
+        let res = PageFrontMatter::parse(content).unwrap();
+        let day = |y, m, d| NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0);
+        assert_eq!(res.publish_datetime, Some(day(2026, 12, 1)));
+        assert!(!res.is_published_at(day(2026, 11, 30)));
+        assert!(res.is_published_at(day(2026, 12, 1)));
+        assert!(res.is_published_at(day(2027, 1, 1)));
+        assert!(!res.is_published_at(NaiveDate::from_ymd(2027, 1, 1).and_hms(12, 0, 0)));
+    }
+
+    #[test_case(&RawFrontMatter::Toml(r#"
+title = "Hello"
+publish_date = 2027-01-01T09:00:00+05:00
+"#); "toml")]
+    #[test_case(&RawFrontMatter::Yaml(r#"
+title: Hello
+publish_date: 2027-01-01T09:00:00+05:00
+"#); "yaml")]
+    fn can_parse_publish_date_with_offset(content: &RawFrontMatter) {
+        let res = PageFrontMatter::parse(content).unwrap();
+        let publish = Utc.ymd(2027, 1, 1).and_hms(4, 0, 0).with_timezone(&Local).naive_local();
+        assert_eq!(res.publish_datetime, Some(publish));
+        assert!(!res.is_published_at(publish - chrono::Duration::seconds(1)));
+        assert!(res.is_published_at(publish));
+    }
+
+    #[test_case(&RawFrontMatter::Toml(r#"
+title = "Hello"
+publish_date = 2026-12-01
+expiry_date = 2026-11-01
+"#); "toml")]
+    #[test_case(&RawFrontMatter::Yaml(r#"
+title: Hello
+publish_date: 2026-12-01
+expiry_date: 2026-11-01
+"#); "yaml")]
+    fn errors_on_expiry_date_before_publish_date(content: &RawFrontMatter) {
+        let res = PageFrontMatter::parse(content);
+        assert!(res.is_err());
+    }
+
+    #[test_case(&RawFrontMatter::Toml(r#"
+title = "Hello"
 description = "hey there"
 date = "2016-10-10"
 "#); "toml")]
@@ -21,7 +21,7 @@ use config::Config;
 use errors::{Error, Result};
 use utils::fs as ufs;
 
-static RESIZED_SUBDIR: &str = "processed_images";
+pub static RESIZED_SUBDIR: &str = "processed_images";
 const DEFAULT_Q_JPG: u8 = 75;
 
 lazy_static! {
@@ -400,6 +400,11 @@ impl Processor {
         }
     }
 
+    /// The base path of the Zola site, from which the images to process are found
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
     pub fn set_base_url(&mut self, config: &Config) {
         self.base_url = config.make_permalink(RESIZED_SUBDIR);
     }
@@ -587,7 +592,7 @@ pub fn read_image_metadata<P: AsRef<Path>>(path: P) -> Result<ImageMetaResponse>
                 (_, _, Some(view_box)) => Ok((view_box.height, view_box.width)),
                 _ => Err("Invalid dimensions: SVG width/height and viewbox not set.".into()),
             }
-            .map(|(h, w)| ImageMetaResponse::new_svg(h as u32, w as u32))
+            .map(|(h, w)| ImageMetaResponse::new_svg(w as u32, h as u32))
         }
         "webp" => {
             // Unfortunatelly we have to load the entire image here, unlike with the others :|
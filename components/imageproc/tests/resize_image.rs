@@ -155,6 +155,14 @@ fn read_image_metadata_svg() {
     );
 }
 
+#[test]
+fn read_image_metadata_svg_not_square() {
+    assert_eq!(
+        image_meta_test("svg_not_square.svg"),
+        ImageMetaResponse { width: 200, height: 100, format: Some("svg") }
+    );
+}
+
 #[test]
 fn read_image_metadata_webp() {
     assert_eq!(
@@ -1,6 +1,7 @@
 /// A page, can be a blog post or a basic page
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -41,6 +42,8 @@ pub struct Page {
     pub ancestors: Vec<DefaultKey>,
     /// The actual content of the page, in markdown
     pub raw_content: String,
+    /// The number of lines before `raw_content` in the file, the ones of the front matter
+    pub raw_content_line: usize,
     /// All the non-md files we found next to the .md file
     pub assets: Vec<PathBuf>,
     /// All the non-md files we found next to the .md file
@@ -112,12 +115,14 @@ impl Page {
         config: &Config,
         base_path: &Path,
     ) -> Result<Page> {
+        let file_content = content;
         let (meta, content) = split_page_content(file_path, content)?;
         let mut page = Page::new(file_path, meta, base_path);
 
         page.lang = page.file.find_language(config)?;
 
         page.raw_content = content.to_string();
+        page.raw_content_line = file_content[..file_content.len() - content.len()].lines().count();
         let (word_count, reading_time) = get_reading_analytics(&page.raw_content);
         page.word_count = Some(word_count);
         page.reading_time = Some(reading_time);
@@ -226,6 +231,7 @@ impl Page {
         config: &Config,
         anchor_insert: InsertAnchor,
         shortcode_definitions: &HashMap<String, ShortcodeDefinition>,
+        imageproc: Option<&Mutex<imageproc::Processor>>,
     ) -> Result<()> {
         let mut context = RenderContext::new(
             tera,
@@ -237,6 +243,10 @@ impl Page {
         );
         context.set_shortcode_definitions(shortcode_definitions);
         context.set_current_page_path(&self.file.relative);
+        context.set_first_line(self.raw_content_line);
+        if let Some(imageproc) = imageproc {
+            context.set_imageproc(imageproc);
+        }
         context.tera_context.insert("page", &SerializingPage::from_page_basic(self, None));
 
         let res = render_content(&self.raw_content, &context).map_err(|e| {
@@ -312,6 +322,7 @@ impl Page {
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::error::Error;
     use std::fs::{create_dir, File};
     use std::io::Write;
     use std::path::{Path, PathBuf};
@@ -344,6 +355,7 @@ Hello world"#;
             &config,
             InsertAnchor::None,
             &HashMap::new(),
+            None,
         )
         .unwrap();
 
@@ -516,6 +528,7 @@ Hello world
             &config,
             InsertAnchor::None,
             &HashMap::new(),
+            None,
         )
         .unwrap();
         assert_eq!(page.summary, Some("<p>Hello world</p>\n".to_string()));
@@ -546,6 +559,7 @@ And here's another. [^2]
             &config,
             InsertAnchor::None,
             &HashMap::new(),
+            None,
         )
         .unwrap();
         assert_eq!(
@@ -554,6 +568,58 @@ And here's another. [^2]
         );
     }
 
+    #[test]
+    fn has_anchor_of_manual_heading_ids() {
+        let config = Config::default_for_test();
+        let content = r#"
++++
++++
+## Getting started {#setup .step}"#
+            .to_string();
+        let mut page =
+            Page::parse(Path::new("hello.md"), &content, &config, &PathBuf::new()).unwrap();
+        page.render_markdown(
+            &HashMap::default(),
+            &Tera::default(),
+            &config,
+            InsertAnchor::None,
+            &HashMap::new(),
+            None,
+        )
+        .unwrap();
+        assert!(page.has_anchor("setup"));
+        assert!(!page.has_anchor("getting-started"));
+    }
+
+    #[test]
+    fn reports_line_of_invalid_formula_in_file() {
+        let mut config = Config::default_for_test();
+        config.markdown.math = true;
+        let content = r#"
++++
+title = "Hello"
++++
+The $x^2$ formula is valid.
+
+But $\frac{1}$ is not."#
+            .to_string();
+        let mut page =
+            Page::parse(Path::new("hello.md"), &content, &config, &PathBuf::new()).unwrap();
+        assert_eq!(page.raw_content_line, 4);
+        let err = page
+            .render_markdown(
+                &HashMap::default(),
+                &Tera::default(),
+                &config,
+                InsertAnchor::None,
+                &HashMap::new(),
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to render content of hello.md");
+        assert_eq!(err.source().unwrap().to_string(), "Invalid formula `\\frac{1}` at line 7");
+    }
+
     #[test]
     fn page_with_assets_gets_right_info() {
         let tmp_dir = tempdir().expect("create temp dir");
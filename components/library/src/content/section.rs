@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use slotmap::DefaultKey;
 use tera::{Context as TeraContext, Tera};
@@ -32,6 +33,8 @@ pub struct Section {
     pub permalink: String,
     /// The actual content of the page, in markdown
     pub raw_content: String,
+    /// The number of lines before `raw_content` in the file, the ones of the front matter
+    pub raw_content_line: usize,
     /// The HTML rendered of the page
     pub content: String,
     /// All the non-md files we found next to the .md file
@@ -81,10 +84,13 @@ impl Section {
         config: &Config,
         base_path: &Path,
     ) -> Result<Section> {
+        let file_content = content;
         let (meta, content) = split_section_content(file_path, content)?;
         let mut section = Section::new(file_path, meta, base_path);
         section.lang = section.file.find_language(config)?;
         section.raw_content = content.to_string();
+        section.raw_content_line =
+            file_content[..file_content.len() - content.len()].lines().count();
         let (word_count, reading_time) = get_reading_analytics(&section.raw_content);
         section.word_count = Some(word_count);
         section.reading_time = Some(reading_time);
@@ -148,6 +154,7 @@ impl Section {
         tera: &Tera,
         config: &Config,
         shortcode_definitions: &HashMap<String, ShortcodeDefinition>,
+        imageproc: Option<&Mutex<imageproc::Processor>>,
     ) -> Result<()> {
         let mut context = RenderContext::new(
             tera,
@@ -159,6 +166,10 @@ impl Section {
         );
         context.set_shortcode_definitions(shortcode_definitions);
         context.set_current_page_path(&self.file.relative);
+        context.set_first_line(self.raw_content_line);
+        if let Some(imageproc) = imageproc {
+            context.set_imageproc(imageproc);
+        }
         context.tera_context.insert("section", &SerializingSection::from_section_basic(self, None));
 
         let res = render_content(&self.raw_content, &context).map_err(|e| {
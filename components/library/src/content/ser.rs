@@ -1,5 +1,5 @@
 //! What we are sending to the templates when rendering them
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::collections::HashSet;
 use std::path::Path;
 
@@ -41,6 +41,8 @@ impl<'a> TranslatedContent<'a> {
                 path: &other.file.path,
             });
         }
+        // The keys come from a set, make sure the order is always the same
+        translations.sort_by(|a, b| a.lang.cmp(b.lang));
 
         translations
     }
@@ -60,6 +62,8 @@ impl<'a> TranslatedContent<'a> {
                 path: &other.file.path,
             });
         }
+        // The keys come from a set, make sure the order is always the same
+        translations.sort_by(|a, b| a.lang.cmp(b.lang));
 
         translations
     }
@@ -79,7 +83,8 @@ pub struct SerializingPage<'a> {
     year: Option<i32>,
     month: Option<u32>,
     day: Option<u32>,
-    taxonomies: &'a HashMap<String, Vec<String>>,
+    // Sorted so the output doesn't change between builds
+    taxonomies: BTreeMap<&'a String, &'a Vec<String>>,
     extra: &'a Map<String, Value>,
     path: &'a str,
     components: &'a [String],
@@ -159,7 +164,7 @@ impl<'a> SerializingPage<'a> {
             year,
             month,
             day,
-            taxonomies: &page.meta.taxonomies,
+            taxonomies: page.meta.taxonomies.iter().collect(),
             path: &page.path,
             components: &page.components,
             summary: &page.summary,
@@ -225,7 +230,7 @@ impl<'a> SerializingPage<'a> {
             year,
             month,
             day,
-            taxonomies: &page.meta.taxonomies,
+            taxonomies: page.meta.taxonomies.iter().collect(),
             path: &page.path,
             components: &page.components,
             summary: &page.summary,
@@ -309,6 +309,8 @@ pub fn find_taxonomies(config: &Config, library: &Library) -> Result<Vec<Taxonom
             library,
         ));
     }
+    // `all_taxonomies` is a HashMap so the order would change between builds otherwise
+    taxonomies.sort_by(|a, b| (&a.lang, &a.slug).cmp(&(&b.lang, &b.slug)));
 
     Ok(taxonomies)
 }
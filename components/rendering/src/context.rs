@@ -1,8 +1,10 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 use config::Config;
 use front_matter::InsertAnchor;
+use imageproc::Processor;
 use tera::{Context, Tera};
 use utils::templates::ShortcodeDefinition;
 
@@ -18,6 +20,10 @@ pub struct RenderContext<'a> {
     pub insert_anchor: InsertAnchor,
     pub lang: &'a str,
     pub shortcode_definitions: Cow<'a, HashMap<String, ShortcodeDefinition>>,
+    /// The number of lines before the content in its file, to report where the errors are
+    pub first_line: usize,
+    /// Where the images of the content are found and resized, for `markdown.images`
+    pub imageproc: Option<&'a Mutex<Processor>>,
 }
 
 impl<'a> RenderContext<'a> {
@@ -43,6 +49,8 @@ impl<'a> RenderContext<'a> {
             config,
             lang,
             shortcode_definitions: Cow::Owned(HashMap::new()),
+            first_line: 0,
+            imageproc: None,
         }
     }
 
@@ -57,6 +65,16 @@ impl<'a> RenderContext<'a> {
         self.current_page_path = Some(path);
     }
 
+    /// Same as above
+    pub fn set_first_line(&mut self, first_line: usize) {
+        self.first_line = first_line;
+    }
+
+    /// Same as above
+    pub fn set_imageproc(&mut self, imageproc: &'a Mutex<Processor>) {
+        self.imageproc = Some(imageproc);
+    }
+
     // In use in the markdown filter
     // NOTE: This RenderContext is not i18n-aware, see MarkdownFilter::filter for details
     // If this function is ever used outside of MarkdownFilter, take this into consideration
@@ -71,6 +89,8 @@ impl<'a> RenderContext<'a> {
             config,
             lang: &config.default_language,
             shortcode_definitions: Cow::Owned(HashMap::new()),
+            first_line: 0,
+            imageproc: None,
         }
     }
 }
@@ -0,0 +1,222 @@
+//! The Markdown syntax pulldown-cmark doesn't support, enabled in the `[markdown]` section of the
+//! config: definition lists, `~subscript~`, `^superscript^` and `==mark==`.
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Tag};
+use regex::Regex;
+
+use config::Markdown;
+
+/// The characters delimiting the inline extensions, left alone when escaped
+pub const INLINE_DELIMITERS: [char; 3] = ['~', '^', '='];
+
+pub fn has_inline_extensions(markdown: &Markdown) -> bool {
+    markdown.subscript || markdown.superscript || markdown.mark
+}
+
+/// Whether the event is an inline element, the others starting or ending a block
+fn is_inline(tag: &Tag) -> bool {
+    matches!(tag, Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..))
+}
+
+fn push_text<'a>(events: &mut Vec<Event<'a>>, text: &str) {
+    if !text.is_empty() {
+        events.push(Event::Text(text.to_string().into()));
+    }
+}
+
+/// Renders `~sub~` and `^sup^` in `text`. Like in Pandoc, their content can't have spaces.
+fn push_sub_and_sup<'a>(events: &mut Vec<Event<'a>>, text: &str, markdown: &Markdown) {
+    lazy_static! {
+        static ref SUB_SUP_RE: Regex = Regex::new(r"~([^\s~]+)~|\^([^\s^]+)\^").unwrap();
+    }
+
+    let mut copied_until = 0;
+    for caps in SUB_SUP_RE.captures_iter(text) {
+        let (tag, content) = match (caps.get(1), caps.get(2)) {
+            (Some(content), _) if markdown.subscript => ("sub", content),
+            (_, Some(content)) if markdown.superscript => ("sup", content),
+            _ => continue,
+        };
+        let whole = caps.get(0).unwrap();
+        push_text(events, &text[copied_until..whole.start()]);
+        events.push(Event::Html(format!("<{}>", tag).into()));
+        push_text(events, content.as_str());
+        events.push(Event::Html(format!("</{}>", tag).into()));
+        copied_until = whole.end();
+    }
+    push_text(events, &text[copied_until..]);
+}
+
+/// Renders the inline extensions enabled in the config. The `==` of a mark can be in different
+/// text events, around emphasis or links for example, but need to be at the same nesting level
+/// of the same block.
+pub fn render_inline_extensions<'a>(events: Vec<Event<'a>>, markdown: &Markdown) -> Vec<Event<'a>> {
+    let mut rendered = Vec::with_capacity(events.len());
+    // The index in `rendered` of the `==` opening a mark and at which depth it is
+    let mut open_mark: Option<(usize, usize)> = None;
+    let mut depth: usize = 0;
+
+    for event in events {
+        let text = match event {
+            Event::Text(ref text) if text.contains(&INLINE_DELIMITERS[..]) => text.clone(),
+            Event::Start(ref tag) | Event::End(ref tag) if !is_inline(tag) => {
+                open_mark = None;
+                depth = 0;
+                rendered.push(event);
+                continue;
+            }
+            Event::Start(_) => {
+                depth += 1;
+                rendered.push(event);
+                continue;
+            }
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                if open_mark.map_or(false, |(_, d)| d > depth) {
+                    open_mark = None;
+                }
+                rendered.push(event);
+                continue;
+            }
+            _ => {
+                rendered.push(event);
+                continue;
+            }
+        };
+
+        if !markdown.mark {
+            push_sub_and_sup(&mut rendered, &text, markdown);
+            continue;
+        }
+
+        let mut copied_until = 0;
+        let mut i = 0;
+        while let Some(offset) = text[i..].find('=') {
+            let start = i + offset;
+            let end = start + text[start..].find(|c| c != '=').unwrap_or(text.len() - start);
+            i = end;
+            // Only `==` is a delimiter, not `=` or `===`
+            if end - start != 2 {
+                continue;
+            }
+            let before = text[..start].chars().next_back();
+            let after = text[end..].chars().next();
+            match open_mark {
+                Some((idx, d)) if d == depth && !before.map_or(false, char::is_whitespace) => {
+                    push_sub_and_sup(&mut rendered, &text[copied_until..start], markdown);
+                    rendered[idx] = Event::Html("<mark>".into());
+                    rendered.push(Event::Html("</mark>".into()));
+                    open_mark = None;
+                }
+                None if !after.map_or(true, char::is_whitespace) => {
+                    push_sub_and_sup(&mut rendered, &text[copied_until..start], markdown);
+                    open_mark = Some((rendered.len(), depth));
+                    rendered.push(Event::Text("==".into()));
+                }
+                _ => continue,
+            }
+            copied_until = end;
+        }
+        push_sub_and_sup(&mut rendered, &text[copied_until..], markdown);
+    }
+
+    rendered
+}
+
+/// The content of a paragraph, split in lines
+fn split_lines(events: Vec<Event>) -> Vec<Vec<Event>> {
+    let mut lines = vec![Vec::new()];
+    for event in events {
+        match event {
+            Event::SoftBreak | Event::HardBreak => lines.push(Vec::new()),
+            _ => lines.last_mut().unwrap().push(event),
+        }
+    }
+    lines
+}
+
+/// Whether the line is a definition, starting with `: `
+fn is_definition(line: &[Event]) -> bool {
+    matches!(line.first(), Some(Event::Text(text)) if text.starts_with(": ") || text.as_ref() == ":")
+}
+
+/// Whether the paragraph is made of the lines of terms followed by the lines of definitions
+fn is_definition_list(paragraph: &[Event]) -> bool {
+    let mut line_starts = paragraph
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| matches!(e, Event::SoftBreak | Event::HardBreak))
+        .map(|(idx, _)| idx + 1);
+    !is_definition(paragraph) && line_starts.any(|idx| is_definition(&paragraph[idx..]))
+}
+
+/// Removes the `: ` at the start of a definition
+fn strip_definition_marker(mut line: Vec<Event>) -> Vec<Event> {
+    if let Some(Event::Text(text)) = line.first() {
+        let stripped = text[1..].trim_start().to_string();
+        if stripped.is_empty() {
+            line.remove(0);
+        } else {
+            line[0] = Event::Text(stripped.into());
+        }
+    }
+    line
+}
+
+/// Renders a paragraph made of terms and definitions as a definition list. The lines after
+/// a definition not starting with `: ` are part of it.
+fn render_definition_list<'a>(rendered: &mut Vec<Event<'a>>, paragraph: Vec<Event<'a>>) {
+    let mut in_definition = false;
+    for line in split_lines(paragraph) {
+        if is_definition(&line) {
+            rendered.push(Event::Html(if in_definition { "</dd>\n<dd>" } else { "<dd>" }.into()));
+            rendered.extend(strip_definition_marker(line));
+            in_definition = true;
+        } else if in_definition {
+            rendered.push(Event::SoftBreak);
+            rendered.extend(line);
+        } else {
+            rendered.push(Event::Html("<dt>".into()));
+            rendered.extend(line);
+            rendered.push(Event::Html("</dt>\n".into()));
+        }
+    }
+    rendered.push(Event::Html("</dd>\n".into()));
+}
+
+/// Renders the paragraphs made of terms and definitions as definition lists, merging the ones
+/// following each other in a single list
+pub fn render_definition_lists(events: Vec<Event>) -> Vec<Event> {
+    let mut rendered = Vec::with_capacity(events.len());
+    // The index in `rendered` of the end of the last definition list
+    let mut last_list_end = None;
+    let mut events = events.into_iter();
+
+    while let Some(event) = events.next() {
+        if event != Event::Start(Tag::Paragraph) {
+            rendered.push(event);
+            continue;
+        }
+
+        let paragraph: Vec<_> =
+            events.by_ref().take_while(|e| *e != Event::End(Tag::Paragraph)).collect();
+        if !is_definition_list(&paragraph) {
+            rendered.push(Event::Start(Tag::Paragraph));
+            rendered.extend(paragraph);
+            rendered.push(Event::End(Tag::Paragraph));
+            continue;
+        }
+
+        if last_list_end.map_or(false, |idx| idx + 1 == rendered.len()) {
+            // Continuing the previous list
+            rendered.pop();
+        } else {
+            rendered.push(Event::Html("<dl>\n".into()));
+        }
+        render_definition_list(&mut rendered, paragraph);
+        rendered.push(Event::Html("</dl>\n".into()));
+        last_list_end = Some(rendered.len() - 1);
+    }
+
+    rendered
+}
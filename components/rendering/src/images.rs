@@ -0,0 +1,175 @@
+//! Adding the dimensions of the local images of the content and `loading="lazy"` to them, with
+//! a `srcset` of resized variants when `markdown.image_widths` is set, enabled by `markdown.images`.
+use std::path::{Path, PathBuf};
+
+use imageproc::read_image_metadata;
+use pulldown_cmark::{escape, CowStr, Event, Tag};
+
+use errors::Result;
+
+use crate::context::RenderContext;
+
+/// The formats that are not resized: vectors don't need it and GIFs would lose their animation
+const NOT_RESIZED_EXTENSIONS: [&str; 2] = ["svg", "gif"];
+
+/// Finds the file of a local image, returning its path and its path from the base directory.
+/// The paths starting with `/` are looked for in the `static` directories and the relative ones
+/// next to the content, for the pages and sections in their own directory as that's where their
+/// URL resolves them.
+fn find_image(base_path: &Path, link: &str, context: &RenderContext) -> Option<(PathBuf, String)> {
+    // Skipping external images, `//cdn.com/a.png` and `data:` URLs
+    if link.starts_with("//") || link.split('/').next().unwrap_or("").contains(':') {
+        return None;
+    }
+    let link = link.split(['?', '#']).next().unwrap_or("");
+    if link.is_empty() {
+        return None;
+    }
+
+    let candidates: Vec<PathBuf> = if let Some(absolute) = link.strip_prefix('/') {
+        let mut dirs = vec![Path::new("static").to_path_buf()];
+        if let Some(ref theme) = context.config.theme {
+            dirs.push(Path::new("themes").join(theme).join("static"));
+        }
+        dirs.into_iter().map(|dir| dir.join(absolute)).collect()
+    } else {
+        let current_path = Path::new(context.current_page_path?);
+        let filename = current_path.file_name()?.to_string_lossy();
+        if !filename.starts_with("index.") && !filename.starts_with("_index.") {
+            return None;
+        }
+        vec![Path::new("content").join(current_path.parent()?).join(link)]
+    };
+
+    candidates.into_iter().find_map(|relative| {
+        let path = base_path.join(&relative);
+        if !path.is_file() || !utils::fs::is_path_in_directory(base_path, &path).unwrap_or(false) {
+            return None;
+        }
+        Some((path, relative.to_string_lossy().replace('\\', "/")))
+    })
+}
+
+/// The `srcset` of the image, with its resized variants narrower than it
+fn make_srcset(
+    link: &str,
+    path: &Path,
+    unified_path: &str,
+    width: u32,
+    context: &RenderContext,
+) -> Result<Option<String>> {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    let widths: Vec<u32> =
+        context.config.markdown.image_widths.iter().copied().filter(|w| *w < width).collect();
+    if widths.is_empty() || extension.map_or(false, |e| NOT_RESIZED_EXTENSIONS.contains(&&*e)) {
+        return Ok(None);
+    }
+
+    let mut imageproc = context.imageproc.unwrap().lock().expect("Couldn't lock imageproc");
+    let mut srcset = Vec::with_capacity(widths.len() + 1);
+    for w in widths {
+        let response = imageproc.enqueue(
+            unified_path.to_string(),
+            path.to_path_buf(),
+            "fit_width",
+            Some(w),
+            None,
+            "auto",
+            None,
+        )?;
+        srcset.push(format!("{} {}w", response.url, response.width));
+    }
+    srcset.push(format!("{} {}w", link, width));
+    Ok(Some(srcset.join(", ")))
+}
+
+/// Renders the image of the `link` whose alt text is `alt`
+fn render_image(
+    link: &str,
+    title: &str,
+    alt: &str,
+    path: &Path,
+    unified_path: &str,
+    context: &RenderContext,
+) -> Result<String> {
+    let meta = read_image_metadata(path)?;
+
+    let mut html = String::from("<img src=\"");
+    escape::escape_href(&mut html, link).expect("Could not write to buffer");
+    html.push_str("\" alt=\"");
+    escape::escape_html(&mut html, alt).expect("Could not write to buffer");
+    if !title.is_empty() {
+        html.push_str("\" title=\"");
+        escape::escape_html(&mut html, title).expect("Could not write to buffer");
+    }
+    html.push_str(&format!(
+        "\" width=\"{}\" height=\"{}\" loading=\"lazy\"",
+        meta.width, meta.height
+    ));
+
+    if let Some(srcset) = make_srcset(link, path, unified_path, meta.width, context)? {
+        html.push_str(" srcset=\"");
+        escape::escape_html(&mut html, &srcset).expect("Could not write to buffer");
+        html.push('"');
+        if let Some(ref sizes) = context.config.markdown.image_sizes {
+            html.push_str(" sizes=\"");
+            escape::escape_html(&mut html, sizes).expect("Could not write to buffer");
+            html.push('"');
+        }
+    }
+    html.push_str(" />");
+
+    Ok(html)
+}
+
+/// Replaces the local images found by their HTML with their dimensions, leaving the others to
+/// pulldown-cmark
+pub fn enhance_images<'a>(
+    events: Vec<Event<'a>>,
+    context: &RenderContext,
+) -> Result<Vec<Event<'a>>> {
+    let base_path = match context.imageproc {
+        Some(imageproc) => {
+            imageproc.lock().expect("Couldn't lock imageproc").base_path().to_path_buf()
+        }
+        None => return Ok(events),
+    };
+
+    let mut enhanced = Vec::with_capacity(events.len());
+    let mut events = events.into_iter();
+    while let Some(event) = events.next() {
+        let (link, title): (CowStr, CowStr) = match event {
+            Event::Start(Tag::Image(_, ref link, ref title)) => (link.clone(), title.clone()),
+            _ => {
+                enhanced.push(event);
+                continue;
+            }
+        };
+        let (path, unified_path) = match find_image(&base_path, &link, context) {
+            Some(found) => found,
+            None => {
+                enhanced.push(event);
+                continue;
+            }
+        };
+
+        // Like pulldown-cmark, the alt text is the text of the events until the end of the image
+        let mut alt = String::new();
+        let mut nesting = 0;
+        for event in events.by_ref() {
+            match event {
+                Event::Start(Tag::Image(..)) => nesting += 1,
+                Event::End(Tag::Image(..)) if nesting == 0 => break,
+                Event::End(Tag::Image(..)) => nesting -= 1,
+                Event::Text(text) | Event::Code(text) | Event::Html(text) => alt.push_str(&text),
+                Event::SoftBreak | Event::HardBreak => alt.push(' '),
+                _ => (),
+            }
+        }
+
+        let html = render_image(&link, &title, &alt, &path, &unified_path, context)?;
+        enhanced.push(Event::Html(html.into()));
+    }
+
+    Ok(enhanced)
+}
@@ -1,9 +1,15 @@
 mod codeblock;
 mod context;
+mod extensions;
+mod images;
 mod markdown;
+mod math;
 mod shortcode;
 mod table_of_contents;
 
+use std::borrow::Cow;
+
+use math::render_math;
 use shortcode::{extract_shortcodes, insert_md_shortcodes};
 
 use errors::Result;
@@ -14,21 +20,29 @@ pub use markdown::Rendered;
 pub use table_of_contents::Heading;
 
 pub fn render_content(content: &str, context: &RenderContext) -> Result<markdown::Rendered> {
+    // The formulas are taken out first so neither the shortcodes nor the Markdown interpret them
+    let (content, math) = if context.config.markdown.math {
+        let (content, math) = render_math(content, context.first_line)?;
+        (Cow::Owned(content), math)
+    } else {
+        (Cow::Borrowed(content), Vec::new())
+    };
+
     // avoid parsing the content if needed
     if !content.contains("{{") && !content.contains("{%") {
-        return markdown_to_html(content, context, Vec::new());
+        return markdown_to_html(&content, context, Vec::new(), math);
     }
 
     let definitions = context.shortcode_definitions.as_ref();
     // Extract all the defined shortcodes
-    let (content, shortcodes) = extract_shortcodes(content, definitions)?;
+    let (content, shortcodes) = extract_shortcodes(&content, definitions)?;
 
     // Step 1: we render the MD shortcodes before rendering the markdown so they can get processed
     let (content, html_shortcodes) =
         insert_md_shortcodes(content, shortcodes, &context.tera_context, &context.tera)?;
 
     // Step 2: we render the markdown and the HTML markdown at the same time
-    let html_context = markdown_to_html(&content, context, html_shortcodes)?;
+    let html_context = markdown_to_html(&content, context, html_shortcodes, math)?;
 
     // TODO: Here issue #1418 could be implemented
     // if do_warn_about_unprocessed_md {
@@ -11,10 +11,18 @@ use utils::vec::InsertMany;
 
 use self::cmark::{Event, LinkType, Options, Parser, Tag};
 use crate::codeblock::{CodeBlock, FenceSettings};
+use crate::extensions::{
+    has_inline_extensions, render_definition_lists, render_inline_extensions, INLINE_DELIMITERS,
+};
+use crate::images::enhance_images;
+use crate::math::{insert_math, with_math_source, Math};
 use crate::shortcode::{Shortcode, SHORTCODE_PLACEHOLDER};
 
 const CONTINUE_READING: &str = "<span id=\"continue-reading\"></span>";
 const ANCHOR_LINK_TEMPLATE: &str = "anchor-link.html";
+const ADMONITION_TEMPLATE: &str = "admonition.html";
+/// Given to the admonition template as `content`, to find where the content goes in its output
+const ADMONITION_CONTENT: &str = "ZOLA_ADMONITION_CONTENT";
 
 #[derive(Debug)]
 pub struct Rendered {
@@ -34,11 +42,49 @@ struct HeadingRef {
     end_idx: usize,
     level: u32,
     id: Option<String>,
+    classes: Vec<String>,
 }
 
 impl HeadingRef {
     fn new(start: usize, level: u32) -> HeadingRef {
-        HeadingRef { start_idx: start, end_idx: 0, level, id: None }
+        HeadingRef { start_idx: start, end_idx: 0, level, id: None, classes: Vec::new() }
+    }
+}
+
+/// The `{#id .class}` attributes at the end of a heading
+#[derive(Debug, PartialEq)]
+struct HeadingAttributes {
+    /// Where they start in the text of the heading
+    start: usize,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+impl HeadingAttributes {
+    /// Parses the attributes at the end of `text`, made of at most one `#id` and any number
+    /// of `.class` separated by spaces
+    fn parse(text: &str) -> Option<HeadingAttributes> {
+        if !text.ends_with('}') {
+            return None;
+        }
+        let start = text.rfind('{')?;
+        let mut attributes = HeadingAttributes { start, id: None, classes: Vec::new() };
+        for token in text[start + 1..text.len() - 1].split_whitespace() {
+            if let Some(id) = token.strip_prefix('#') {
+                if attributes.id.is_some() {
+                    return None;
+                }
+                attributes.id = Some(id.to_string());
+            } else if let Some(class) = token.strip_prefix('.').filter(|c| !c.is_empty()) {
+                attributes.classes.push(class.to_string());
+            } else {
+                return None;
+            }
+        }
+        if attributes.id.is_none() && attributes.classes.is_empty() {
+            return None;
+        }
+        Some(attributes)
     }
 }
 
@@ -141,10 +187,83 @@ fn get_heading_refs(events: &[Event]) -> Vec<HeadingRef> {
     heading_refs
 }
 
+/// Renders the admonition template for `kind`, split around its content
+fn render_admonition(kind: &str, context: &RenderContext) -> Result<(String, String)> {
+    let mut c = tera::Context::new();
+    c.insert("kind", kind);
+    c.insert("content", ADMONITION_CONTENT);
+    c.insert("lang", &context.lang);
+
+    let html = utils::templates::render_template(ADMONITION_TEMPLATE, &context.tera, c, &None)
+        .map_err(|e| Error::chain("Failed to render admonition template", e))?;
+    match html.split_once(ADMONITION_CONTENT) {
+        Some((start, end)) => Ok((start.to_string(), end.to_string())),
+        None => {
+            Err(format!("The `{}` template needs to render `content`", ADMONITION_TEMPLATE).into())
+        }
+    }
+}
+
+/// Turns the blockquotes whose first line is a `[!TYPE]` marker, TYPE being one of the configured
+/// admonition types, into admonitions. The marker is removed and the rest of the blockquote
+/// is rendered in the admonition template.
+fn render_admonitions(events: &mut [Event], context: &RenderContext) -> Result<()> {
+    // The end of the admonitions, or `None` for plain blockquotes, of the blockquotes we are in
+    let mut blockquote_ends: Vec<Option<String>> = Vec::new();
+    for i in 0..events.len() {
+        match events[i] {
+            Event::Start(Tag::BlockQuote) => {
+                let marker_end = match events.get(i + 1) {
+                    Some(Event::Start(Tag::Paragraph)) => events[i + 2..]
+                        .iter()
+                        .position(|e| !matches!(e, Event::Text(_)))
+                        .map(|len| i + 2 + len),
+                    _ => None,
+                };
+                let kind = marker_end.and_then(|end| {
+                    let marker = get_text(&events[i + 2..end]);
+                    context.config.markdown.admonition_type(marker.trim())
+                });
+                let (kind, marker_end) = match (kind, marker_end) {
+                    (Some(kind), Some(end)) => (kind, end),
+                    _ => {
+                        blockquote_ends.push(None);
+                        continue;
+                    }
+                };
+                // The paragraph of the marker goes away with it if the marker is alone in it
+                let first_removed = match events[marker_end] {
+                    Event::SoftBreak | Event::HardBreak => i + 2,
+                    Event::End(Tag::Paragraph) => i + 1,
+                    _ => {
+                        blockquote_ends.push(None);
+                        continue;
+                    }
+                };
+
+                let (start, end) = render_admonition(&kind, context)?;
+                events[i] = Event::Html(start.into());
+                for event in &mut events[first_removed..=marker_end] {
+                    *event = Event::Html("".into());
+                }
+                blockquote_ends.push(Some(end));
+            }
+            Event::End(Tag::BlockQuote) => {
+                if let Some(Some(end)) = blockquote_ends.pop() {
+                    events[i] = Event::Html(end.into());
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
 pub fn markdown_to_html(
     content: &str,
     context: &RenderContext,
     html_shortcodes: Vec<Shortcode>,
+    math: Vec<Math>,
 ) -> Result<Rendered> {
     lazy_static! {
         static ref EMOJI_REPLACER: gh_emoji::Replacer = gh_emoji::Replacer::new();
@@ -245,6 +364,20 @@ pub fn markdown_to_html(
                         let html = code_block.highlight(&text);
                         events.push(Event::Html(html.into()));
                     } else {
+                        // pulldown-cmark starts a new text right after a backslash escape: an
+                        // escaped delimiter of the inline extensions is kept out of their way
+                        let text = match text.chars().next() {
+                            Some(c)
+                                if INLINE_DELIMITERS.contains(&c)
+                                    && has_inline_extensions(&context.config.markdown)
+                                    && content[..range.start].ends_with('\\') =>
+                            {
+                                events.push(Event::Html(c.to_string().into()));
+                                range.start += 1;
+                                text[1..].to_string().into()
+                            }
+                            _ => text,
+                        };
                         let text = if context.config.markdown.render_emoji {
                             EMOJI_REPLACER.replace_all(&text).to_string().into()
                         } else {
@@ -365,25 +498,36 @@ pub fn markdown_to_html(
             })
             .collect();
 
+        if context.config.markdown.images {
+            events = enhance_images(events, context)?;
+        }
+        if context.config.markdown.definition_lists {
+            events = render_definition_lists(events);
+        }
+        if has_inline_extensions(&context.config.markdown) {
+            events = render_inline_extensions(events, &context.config.markdown);
+        }
+
         let mut heading_refs = get_heading_refs(&events);
 
         let mut anchors_to_insert = vec![];
 
-        // First heading pass: look for a manually-specified IDs, e.g. `# Heading text {#hash}`
+        // First heading pass: look for manually-specified IDs and classes,
+        // e.g. `# Heading text {#hash .class}`
         // (This is a separate first pass so that auto IDs can avoid collisions with manual IDs.)
         for heading_ref in heading_refs.iter_mut() {
             let end_idx = heading_ref.end_idx;
             if let Event::Text(ref mut text) = events[end_idx - 1] {
-                if text.as_bytes().last() == Some(&b'}') {
-                    if let Some(mut i) = text.find("{#") {
-                        let id = text[i + 2..text.len() - 1].to_owned();
-                        inserted_anchors.push(id.clone());
-                        while i > 0 && text.as_bytes()[i - 1] == b' ' {
-                            i -= 1;
+                if let Some(attributes) = HeadingAttributes::parse(text) {
+                    if let Some(ref id) = attributes.id {
+                        if inserted_anchors.contains(id) {
+                            return Err(format!("Duplicate heading id `{}`", id).into());
                         }
-                        heading_ref.id = Some(id);
-                        *text = text[..i].to_owned().into();
+                        inserted_anchors.push(id.clone());
                     }
+                    heading_ref.id = attributes.id;
+                    heading_ref.classes = attributes.classes;
+                    *text = text[..attributes.start].trim_end_matches(' ').to_owned().into();
                 }
             }
         }
@@ -392,7 +536,10 @@ pub fn markdown_to_html(
         for heading_ref in heading_refs {
             let start_idx = heading_ref.start_idx;
             let end_idx = heading_ref.end_idx;
-            let title = get_text(&events[start_idx + 1..end_idx]);
+            let mut title = get_text(&events[start_idx + 1..end_idx]);
+            if !math.is_empty() {
+                title = with_math_source(&title, &math).into_owned();
+            }
             let id = heading_ref.id.unwrap_or_else(|| {
                 find_anchor(
                     &inserted_anchors,
@@ -402,8 +549,15 @@ pub fn markdown_to_html(
             });
             inserted_anchors.push(id.clone());
 
-            // insert `id` to the tag
-            let html = format!("<h{lvl} id=\"{id}\">", lvl = heading_ref.level, id = id);
+            // insert `id` and the classes to the tag
+            let mut html = format!("<h{lvl} id=\"{id}\"", lvl = heading_ref.level, id = id);
+            if !heading_ref.classes.is_empty() {
+                html.push_str(" class=\"");
+                cmark::escape::escape_html(&mut html, &heading_ref.classes.join(" "))
+                    .expect("Could not write to buffer");
+                html.push('"');
+            }
+            html.push('>');
             events[start_idx] = Event::Html(html.into());
 
             // generate anchors and places to insert them
@@ -439,6 +593,14 @@ pub fn markdown_to_html(
             events.insert_many(anchors_to_insert);
         }
 
+        if !math.is_empty() {
+            events = insert_math(events, &math);
+        }
+
+        if context.config.markdown.render_admonitions {
+            render_admonitions(&mut events, context)?;
+        }
+
         cmark::html::push_html(&mut html, events.into_iter());
     }
 
@@ -459,6 +621,29 @@ pub fn markdown_to_html(
 mod tests {
     use super::*;
 
+    #[test]
+    fn can_parse_heading_attributes() {
+        assert_eq!(
+            HeadingAttributes::parse("Title {#custom-id .wide .note}"),
+            Some(HeadingAttributes {
+                start: 6,
+                id: Some("custom-id".to_string()),
+                classes: vec!["wide".to_string(), "note".to_string()]
+            })
+        );
+        assert_eq!(
+            HeadingAttributes::parse("Title{.note}"),
+            Some(HeadingAttributes { start: 5, id: None, classes: vec!["note".to_string()] })
+        );
+        assert_eq!(
+            HeadingAttributes::parse("{#}"),
+            Some(HeadingAttributes { start: 0, id: Some(String::new()), classes: vec![] })
+        );
+        for text in &["Title", "Title {}", "Title {#a #b}", "Title {#a b}", "Title {.}", "{#a} b"] {
+            assert_eq!(HeadingAttributes::parse(text), None);
+        }
+    }
+
     #[test]
     fn test_is_external_link() {
         assert!(is_external_link("http://example.com/"));
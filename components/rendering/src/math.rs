@@ -0,0 +1,257 @@
+//! Rendering of the `$...$` and `$$...$$` LaTeX formulas to MathML at build time.
+//! The formulas are replaced by placeholders before the Markdown is parsed so it doesn't
+//! interpret their `_` and `*`, and the placeholders by the MathML while rendering it.
+use std::borrow::Cow;
+use std::ops::Range;
+
+use latex2mathml::{latex_to_mathml, DisplayStyle};
+use lazy_static::lazy_static;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+
+use errors::{Error, Result};
+
+lazy_static! {
+    pub static ref MATH_PLACEHOLDER_RE: Regex = Regex::new(r"@@ZOLA_MATH_(\d+)@@").unwrap();
+}
+
+/// A formula rendered to MathML
+#[derive(Debug, PartialEq)]
+pub struct Math {
+    /// The LaTeX, without the dollars
+    pub source: String,
+    pub mathml: String,
+    pub display: bool,
+}
+
+/// Puts back the LaTeX of the formulas in `text`, for the places where it can't be MathML
+/// like the titles of the table of contents
+pub fn with_math_source<'t>(text: &'t str, math: &[Math]) -> Cow<'t, str> {
+    MATH_PLACEHOLDER_RE.replace_all(text, |caps: &regex::Captures| {
+        let idx: usize = caps[1].parse().unwrap();
+        math[idx].source.clone()
+    })
+}
+
+/// The ranges of `content` where dollars are not math: code and raw HTML
+fn ignored_ranges(content: &str) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut opts = Options::empty();
+    opts.insert(Options::ENABLE_TABLES);
+    opts.insert(Options::ENABLE_FOOTNOTES);
+    for (event, range) in Parser::new_ext(content, opts).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) | Event::Html(_) => ranges.push(range),
+            _ => (),
+        }
+    }
+    ranges
+}
+
+/// Finds where the formula starting with the `delimiter` at `start` ends, following the rules of
+/// Pandoc for inline math: the opening `$` needs to be followed by a non-space character and the
+/// closing one preceded by one and not followed by a digit, so prices are left alone.
+fn find_closing(content: &str, start: usize, delimiter: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let inner_start = start + delimiter.len();
+    if delimiter == "$" && bytes.get(inner_start).map_or(true, |b| b.is_ascii_whitespace()) {
+        return None;
+    }
+
+    let mut i = inner_start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            // Formulas don't span paragraphs
+            b'\n' if content[i + 1..].trim_start_matches([' ', '\t', '\r']).starts_with('\n') => {
+                return None
+            }
+            b'$' if content[i..].starts_with(delimiter) && i > inner_start => {
+                if delimiter == "$"
+                    && (bytes[i - 1].is_ascii_whitespace()
+                        || bytes.get(i + 1).map_or(false, |b| b.is_ascii_digit()))
+                {
+                    i += 1;
+                    continue;
+                }
+                return Some(i);
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Replaces the placeholders of the formulas in the text and HTML events by their MathML.
+/// A display formula alone in its paragraph replaces the paragraph.
+pub fn insert_math<'a>(events: Vec<Event<'a>>, math: &[Math]) -> Vec<Event<'a>> {
+    let mut with_math = Vec::with_capacity(events.len());
+    let mut skip_next_end_p = false;
+    let mut events = events.into_iter().peekable();
+    while let Some(event) = events.next() {
+        let (text, is_text) = match event {
+            Event::Text(ref text) if text.contains("@@ZOLA_MATH_") => (text.clone(), true),
+            Event::Html(ref text) if text.contains("@@ZOLA_MATH_") => (text.clone(), false),
+            Event::End(Tag::Paragraph) if skip_next_end_p => {
+                skip_next_end_p = false;
+                continue;
+            }
+            _ => {
+                with_math.push(event);
+                continue;
+            }
+        };
+
+        if let Some(caps) = MATH_PLACEHOLDER_RE.captures(&text) {
+            let formula = &math[caps[1].parse::<usize>().unwrap()];
+            if formula.display
+                && caps[0].len() == text.len()
+                && matches!(with_math.last(), Some(Event::Start(Tag::Paragraph)))
+                && matches!(events.peek(), Some(Event::End(Tag::Paragraph)))
+            {
+                with_math.pop();
+                with_math.push(Event::Html(format!("{}\n", formula.mathml).into()));
+                skip_next_end_p = true;
+                continue;
+            }
+        }
+
+        let mut copied_until = 0;
+        for caps in MATH_PLACEHOLDER_RE.captures_iter(&text) {
+            let placeholder = caps.get(0).unwrap();
+            if placeholder.start() > copied_until {
+                let before = text[copied_until..placeholder.start()].to_string().into();
+                with_math.push(if is_text { Event::Text(before) } else { Event::Html(before) });
+            }
+            let formula = &math[caps[1].parse::<usize>().unwrap()];
+            with_math.push(Event::Html(formula.mathml.clone().into()));
+            copied_until = placeholder.end();
+        }
+        if copied_until < text.len() {
+            let after = text[copied_until..].to_string().into();
+            with_math.push(if is_text { Event::Text(after) } else { Event::Html(after) });
+        }
+    }
+    with_math
+}
+
+/// latex2mathml only returns an error for some invalid formulas, writing `[PARSE ERROR: ...]`
+/// in the MathML for the others
+fn find_parse_error(mathml: &str) -> Option<String> {
+    lazy_static! {
+        static ref PARSE_ERROR_RE: Regex =
+            Regex::new(r#"\[PARSE ERROR: Undefined\("(.*?)"\)\]</mtext>"#).unwrap();
+        static ref COMMAND_RE: Regex = Regex::new(r#"^Command\(\\"(.*)\\"\)$"#).unwrap();
+    }
+
+    let token = PARSE_ERROR_RE.captures(mathml)?.get(1).unwrap().as_str();
+    Some(match COMMAND_RE.captures(token) {
+        Some(caps) => format!("Unknown command `\\{}`", &caps[1]),
+        None if token == "EOF" => "The formula ends too early".to_string(),
+        None => format!("Unexpected `{}`", token),
+    })
+}
+
+/// Renders the formulas of `content` to MathML, returning the content with a placeholder in
+/// place of every formula and the formulas. `first_line` is the number of lines before `content`
+/// in its file, to report the line of the invalid formulas.
+pub fn render_math(content: &str, first_line: usize) -> Result<(String, Vec<Math>)> {
+    if !content.contains('$') {
+        return Ok((content.to_string(), Vec::new()));
+    }
+
+    let ignored = ignored_ranges(content);
+    let mut output = String::with_capacity(content.len());
+    let mut formulas = Vec::new();
+    let mut copied_until = 0;
+    let mut i = 0;
+    while let Some(offset) = content[i..].find(['$', '\\']) {
+        let start = i + offset;
+        if let Some(range) = ignored.iter().find(|r| r.contains(&start)) {
+            i = range.end;
+            continue;
+        }
+        // An escaped dollar
+        if content[start..].starts_with('\\') {
+            i = start + 1 + content[start + 1..].chars().next().map_or(0, |c| c.len_utf8());
+            continue;
+        }
+
+        let delimiter = if content[start..].starts_with("$$") { "$$" } else { "$" };
+        let end = match find_closing(content, start, delimiter) {
+            Some(end) if !ignored.iter().any(|r| r.start > start && r.start < end) => end,
+            _ => {
+                i = start + delimiter.len();
+                continue;
+            }
+        };
+
+        let latex = content[start + delimiter.len()..end].trim();
+        let display = delimiter == "$$";
+        let style = if display { DisplayStyle::Block } else { DisplayStyle::Inline };
+        let error = |reason: String| {
+            let line = first_line + content[..start].matches('\n').count() + 1;
+            Error::chain(format!("Invalid formula `{}` at line {}", latex, line), reason)
+        };
+        let mathml = latex_to_mathml(latex, style).map_err(|e| error(e.to_string()))?;
+        if let Some(reason) = find_parse_error(&mathml) {
+            return Err(error(reason));
+        }
+
+        output.push_str(&content[copied_until..start]);
+        output.push_str(&format!("@@ZOLA_MATH_{}@@", formulas.len()));
+        formulas.push(Math { source: latex.to_string(), mathml, display });
+        i = end + delimiter.len();
+        copied_until = i;
+    }
+    output.push_str(&content[copied_until..]);
+
+    Ok((output, formulas))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as StdError;
+
+    use super::*;
+
+    fn placeholders(content: &str) -> String {
+        render_math(content, 0).unwrap().0
+    }
+
+    #[test]
+    fn can_find_formulas() {
+        assert_eq!(placeholders("Let $x_1$ be"), "Let @@ZOLA_MATH_0@@ be");
+        assert_eq!(placeholders("$$\na^2 + b^2\n$$"), "@@ZOLA_MATH_0@@");
+        assert_eq!(placeholders("$a$ and $$b$$"), "@@ZOLA_MATH_0@@ and @@ZOLA_MATH_1@@");
+    }
+
+    #[test]
+    fn leaves_prices_and_escaped_dollars_alone() {
+        for content in &["It costs $5 or $10.", "$ x $", "It's \\$x\\$", "$x$5", "$x\n\ny$"] {
+            assert_eq!(placeholders(content), *content);
+        }
+    }
+
+    #[test]
+    fn leaves_code_alone() {
+        for content in &["`$x$`", "```\n$x$\n```", "    $x$", "<span title=\"$x$\">"] {
+            assert_eq!(placeholders(content), *content);
+        }
+        assert_eq!(placeholders("$a$ `$b$` $c$"), "@@ZOLA_MATH_0@@ `$b$` @@ZOLA_MATH_1@@");
+    }
+
+    #[test]
+    fn reports_line_of_invalid_formulas() {
+        let err = render_math("Some text\n\nThe $\\frac{1}$ formula", 3).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid formula `\\frac{1}` at line 6");
+        assert_eq!(err.source().unwrap().to_string(), "The formula ends too early");
+
+        let err = render_math("$$\n\\foo{x}\n$$", 0).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid formula `\\foo{x}` at line 1");
+        assert_eq!(err.source().unwrap().to_string(), "Unknown command `\\foo`");
+
+        let err = render_math("$x}$", 0).unwrap_err();
+        assert_eq!(err.source().unwrap().to_string(), "Unexpected `RBrace`");
+    }
+}
@@ -585,15 +585,15 @@ fn can_handle_manual_ids_on_headings() {
         InsertAnchor::None,
     );
     // Tested things: manual IDs; whitespace flexibility; that automatic IDs avoid collision with
-    // manual IDs; that duplicates are in fact permitted among manual IDs; that any non-plain-text
-    // in the middle of `{#…}` will disrupt it from being acknowledged as a manual ID (that last
-    // one could reasonably be considered a bug rather than a feature, but test it either way); one
-    // workaround for the improbable case where you actually want `{#…}` at the end of a heading.
+    // manual IDs; that any non-plain-text in the middle of `{#…}` will disrupt it from being
+    // acknowledged as a manual ID (that last one could reasonably be considered a bug rather than
+    // a feature, but test it either way); one workaround for the improbable case where you
+    // actually want `{#…}` at the end of a heading.
     let res = render_content(
         "\
          # Hello\n\
          # Hello{#hello}\n\
-         # Hello {#hello}\n\
+         # Hello {#hello-again}\n\
          # Hello     {#Something_else} \n\
          # Workaround for literal {#…&#125;\n\
          # Hello\n\
@@ -606,7 +606,7 @@ fn can_handle_manual_ids_on_headings() {
         "\
          <h1 id=\"hello-1\">Hello</h1>\n\
          <h1 id=\"hello\">Hello</h1>\n\
-         <h1 id=\"hello\">Hello</h1>\n\
+         <h1 id=\"hello-again\">Hello</h1>\n\
          <h1 id=\"Something_else\">Hello</h1>\n\
          <h1 id=\"workaround-for-literal\">Workaround for literal {#…}</h1>\n\
          <h1 id=\"hello-2\">Hello</h1>\n\
@@ -615,6 +615,55 @@ fn can_handle_manual_ids_on_headings() {
     );
 }
 
+#[test]
+fn can_set_heading_classes() {
+    let tera_ctx = Tera::default();
+    let permalinks_ctx = HashMap::new();
+    let config = Config::default_for_test();
+    let context = RenderContext::new(
+        &tera_ctx,
+        &config,
+        &config.default_language,
+        "https://mysite.com/",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content(
+        "# Install {#setup .step .wide}\n## Configure {.step}\n## Title {.a #b c}",
+        &context,
+    )
+    .unwrap();
+    assert_eq!(
+        res.body,
+        "<h1 id=\"setup\" class=\"step wide\">Install</h1>\n\
+         <h2 id=\"configure\" class=\"step\">Configure</h2>\n\
+         <h2 id=\"title-a-b-c\">Title {.a #b c}</h2>\n"
+    );
+    assert_eq!(res.toc[0].id, "setup");
+    assert_eq!(res.toc[0].permalink, "https://mysite.com/#setup");
+    assert_eq!(res.toc[0].title, "Install");
+}
+
+#[test]
+fn errors_on_duplicate_manual_ids() {
+    let tera_ctx = Tera::default();
+    let permalinks_ctx = HashMap::new();
+    let config = Config::default_for_test();
+    let context = RenderContext::new(
+        &tera_ctx,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let err = render_content("# Hello {#hello}\n# Goodbye {#hello .bye}", &context).unwrap_err();
+    assert_eq!(err.to_string(), "Duplicate heading id `hello`");
+    // Automatic ids can still be the same as a manual one, they get a number
+    let res = render_content("# Hello\n# Goodbye {#hello}", &context).unwrap();
+    assert_eq!(res.body, "<h1 id=\"hello-1\">Hello</h1>\n<h1 id=\"hello\">Goodbye</h1>\n");
+}
+
 #[test]
 fn blank_headings() {
     let tera_ctx = Tera::default();
@@ -1702,3 +1751,172 @@ fn html_shortcode_regression() {
     let res = render_content(markdown_string, &context).unwrap();
     assert_eq!(res.body, "<p>a.2 b.1 c.3</p>\n");
 }
+
+#[test]
+fn can_render_admonitions() {
+    let permalinks_ctx = HashMap::new();
+    let mut config = Config::default_for_test();
+    config.markdown.render_admonitions = true;
+    let context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content(
+        r#"
+> [!NOTE]
+> Useful *information*.
+
+> [!warning]
+>
+> Be careful.
+>
+> Really.
+
+> Just a quote."#,
+        &context,
+    )
+    .unwrap();
+    assert_eq!(
+        res.body,
+        r#"<aside class="admonition admonition-note">
+<p class="admonition-title">Note</p>
+<p>Useful <em>information</em>.</p>
+</aside>
+<aside class="admonition admonition-warning">
+<p class="admonition-title">Warning</p>
+<p>Be careful.</p>
+<p>Really.</p>
+</aside>
+<blockquote>
+<p>Just a quote.</p>
+</blockquote>
+"#
+    );
+}
+
+#[test]
+fn only_renders_configured_admonition_types() {
+    let permalinks_ctx = HashMap::new();
+    let mut config = Config::default_for_test();
+    config.markdown.render_admonitions = true;
+    config.markdown.admonition_types = vec!["Example".to_string()];
+    let context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content("> [!EXAMPLE]\n> Hello\n\n> [!NOTE]\n> Hello", &context).unwrap();
+    assert!(res.body.starts_with("<aside class=\"admonition admonition-example\">"));
+    assert!(res.body.contains("<blockquote>\n<p>[!NOTE]\nHello</p>\n</blockquote>"));
+
+    config.markdown.render_admonitions = false;
+    let context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content("> [!EXAMPLE]\n> Hello", &context).unwrap();
+    assert_eq!(res.body, "<blockquote>\n<p>[!EXAMPLE]\nHello</p>\n</blockquote>\n");
+}
+
+#[test]
+fn can_use_admonition_template() {
+    let mut tera = Tera::default();
+    tera.extend(&ZOLA_TERA).unwrap();
+    tera.add_raw_template(
+        "admonition.html",
+        "<div class=\"callout {{ kind }}\" lang=\"{{ lang }}\">{{ content | safe }}</div>",
+    )
+    .unwrap();
+    let permalinks_ctx = HashMap::new();
+    let mut config = Config::default_for_test();
+    config.markdown.render_admonitions = true;
+    let context = RenderContext::new(
+        &tera,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content("> [!TIP]\n> Hello", &context).unwrap();
+    assert_eq!(res.body, "<div class=\"callout tip\" lang=\"en\">\n<p>Hello</p>\n</div>");
+
+    tera.add_raw_template("admonition.html", "<div>{{ kind }}</div>").unwrap();
+    let context = RenderContext::new(
+        &tera,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    assert!(render_content("> [!TIP]\n> Hello", &context).is_err());
+}
+
+#[test]
+fn can_render_math() {
+    let permalinks_ctx = HashMap::new();
+    let mut config = Config::default_for_test();
+    config.markdown.math = true;
+    let context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content(
+        r#"
+## The $x_1$ axis
+
+Let $a_1 * b_1$ be, for $5 or $10, `$c$`:
+
+$$
+\frac{a}{b}
+$$
+
+```
+$d$
+```"#,
+        &context,
+    )
+    .unwrap();
+    assert_eq!(
+        res.body,
+        r#"<h2 id="the-x-1-axis">The <math xmlns="http://www.w3.org/1998/Math/MathML" display="inline"><msub><mi>x</mi><mn>1</mn></msub></math> axis</h2>
+<p>Let <math xmlns="http://www.w3.org/1998/Math/MathML" display="inline"><msub><mi>a</mi><mn>1</mn></msub><mo>*</mo><msub><mi>b</mi><mn>1</mn></msub></math> be, for $5 or $10, <code>$c$</code>:</p>
+<math xmlns="http://www.w3.org/1998/Math/MathML" display="block"><mfrac><mi>a</mi><mi>b</mi></mfrac></math>
+<pre><code>$d$
+</code></pre>
+"#
+    );
+    assert_eq!(res.toc[0].title, "The x_1 axis");
+}
+
+#[test]
+fn doesnt_render_math_by_default() {
+    let permalinks_ctx = HashMap::new();
+    let config = Config::default_for_test();
+    let context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks_ctx,
+        InsertAnchor::None,
+    );
+    let res = render_content("Let $x$ be", &context).unwrap();
+    assert_eq!(res.body, "<p>Let $x$ be</p>\n");
+}
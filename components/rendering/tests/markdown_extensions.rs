@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use config::Config;
+use front_matter::InsertAnchor;
+use rendering::{render_content, RenderContext};
+use templates::ZOLA_TERA;
+
+macro_rules! test_scenario {
+    ($config:expr, $in_str:literal, $out_str:literal) => {
+        let config = $config;
+        let permalinks = HashMap::new();
+        let context = RenderContext::new(
+            &ZOLA_TERA,
+            &config,
+            &config.default_language,
+            "",
+            &permalinks,
+            InsertAnchor::None,
+        );
+
+        let rendered = render_content($in_str, &context);
+        println!("{:?}", rendered);
+        assert!(rendered.is_ok());
+
+        let rendered = rendered.unwrap();
+        assert_eq!(rendered.body, $out_str.to_string());
+    };
+}
+
+fn with_extensions() -> Config {
+    let mut config = Config::default_for_test();
+    config.markdown.definition_lists = true;
+    config.markdown.subscript = true;
+    config.markdown.superscript = true;
+    config.markdown.mark = true;
+    config
+}
+
+#[test]
+fn extensions_are_disabled_by_default() {
+    test_scenario!(
+        Config::default_for_test(),
+        "Term\n: Definition\n\nH~2~O x^2^ ==mark==",
+        "<p>Term\n: Definition</p>\n<p>H~2~O x^2^ ==mark==</p>\n"
+    );
+}
+
+#[test]
+fn definition_lists() {
+    // One term and one definition
+    test_scenario!(
+        with_extensions(),
+        "Apple\n: A fruit",
+        "<dl>\n<dt>Apple</dt>\n<dd>A fruit</dd>\n</dl>\n"
+    );
+
+    // Several terms and definitions, with inline markup
+    test_scenario!(
+        with_extensions(),
+        "`get`\n`fetch`\n: Reads a *value*\n: Never fails",
+        "<dl>\n<dt><code>get</code></dt>\n<dt><code>fetch</code></dt>\n\
+         <dd>Reads a <em>value</em></dd>\n<dd>Never fails</dd>\n</dl>\n"
+    );
+
+    // A definition continues on the lines not starting with `: `
+    test_scenario!(
+        with_extensions(),
+        "Apple\n: A fruit\ngrowing on trees",
+        "<dl>\n<dt>Apple</dt>\n<dd>A fruit\ngrowing on trees</dd>\n</dl>\n"
+    );
+
+    // The lists following each other are merged
+    test_scenario!(
+        with_extensions(),
+        "Apple\n: A fruit\n\nCarrot\n: A vegetable\n\nThe end",
+        "<dl>\n<dt>Apple</dt>\n<dd>A fruit</dd>\n<dt>Carrot</dt>\n<dd>A vegetable</dd>\n</dl>\n\
+         <p>The end</p>\n"
+    );
+
+    // Not a definition list
+    test_scenario!(
+        with_extensions(),
+        ": No term\n\nA ratio of 1:2\n:not a definition",
+        "<p>: No term</p>\n<p>A ratio of 1:2\n:not a definition</p>\n"
+    );
+}
+
+#[test]
+fn subscript_and_superscript() {
+    test_scenario!(
+        with_extensions(),
+        "H~2~O and x^2^ + y^n-1^",
+        "<p>H<sub>2</sub>O and x<sup>2</sup> + y<sup>n-1</sup></p>\n"
+    );
+
+    // No spaces in them, and strikethrough still works
+    test_scenario!(
+        with_extensions(),
+        "a ~b c~ d ^e f^ ~~deleted~~",
+        "<p>a ~b c~ d ^e f^ <del>deleted</del></p>\n"
+    );
+
+    // Escaped delimiters and code are left alone
+    test_scenario!(
+        with_extensions(),
+        "H\\~2~O x\\^2^ `y^2^`",
+        "<p>H~2~O x^2^ <code>y^2^</code></p>\n"
+    );
+
+    // Each one can be enabled on its own
+    let mut config = Config::default_for_test();
+    config.markdown.superscript = true;
+    test_scenario!(config, "H~2~O x^2^", "<p>H~2~O x<sup>2</sup></p>\n");
+}
+
+#[test]
+fn mark() {
+    test_scenario!(
+        with_extensions(),
+        "Some ==highlighted text== here",
+        "<p>Some <mark>highlighted text</mark> here</p>\n"
+    );
+
+    // Around other inline elements
+    test_scenario!(
+        with_extensions(),
+        "==a *very* [important](/) point==",
+        "<p><mark>a <em>very</em> <a href=\"/\">important</a> point</mark></p>\n"
+    );
+
+    // Inside other inline elements, with subscript
+    test_scenario!(
+        with_extensions(),
+        "*==CO~2~==*",
+        "<p><em><mark>CO<sub>2</sub></mark></em></p>\n"
+    );
+
+    // Comparisons, other runs of `=` and delimiters at different levels are left alone
+    test_scenario!(
+        with_extensions(),
+        "a == b, c === d, ==e *f== g*",
+        "<p>a == b, c === d, ==e <em>f== g</em></p>\n"
+    );
+
+    // So are escaped delimiters
+    test_scenario!(with_extensions(), "\\==h==", "<p>==h==</p>\n");
+
+    // Marks don't span paragraphs
+    test_scenario!(with_extensions(), "==a\n\nb==", "<p>==a</p>\n<p>b==</p>\n");
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use config::Config;
+use front_matter::InsertAnchor;
+use imageproc::Processor;
+use regex::Regex;
+use rendering::{render_content, RenderContext};
+use tempfile::{tempdir, TempDir};
+use templates::ZOLA_TERA;
+
+const TEST_IMGS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../imageproc/tests/test_imgs");
+
+/// A site with images in `static` and next to a page in its own directory
+fn make_site() -> TempDir {
+    let dir = tempdir().unwrap();
+    let static_dir = dir.path().join("static").join("images");
+    let page_dir = dir.path().join("content").join("blog").join("post");
+    fs::create_dir_all(&static_dir).unwrap();
+    fs::create_dir_all(&page_dir).unwrap();
+    fs::copy(Path::new(TEST_IMGS).join("jpg.jpg"), static_dir.join("photo.jpg")).unwrap();
+    fs::copy(Path::new(TEST_IMGS).join("png.png"), page_dir.join("diagram.png")).unwrap();
+    fs::write(
+        static_dir.join("logo.svg"),
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100"></svg>"#,
+    )
+    .unwrap();
+    fs::write(static_dir.join("broken.png"), "not an image").unwrap();
+    dir
+}
+
+fn render(content: &str, config: &Config, imageproc: &Mutex<Processor>, page_path: &str) -> String {
+    let permalinks = HashMap::new();
+    let mut context = RenderContext::new(
+        &ZOLA_TERA,
+        config,
+        &config.default_language,
+        "",
+        &permalinks,
+        InsertAnchor::None,
+    );
+    context.set_current_page_path(page_path);
+    context.set_imageproc(imageproc);
+    render_content(content, &context).unwrap().body
+}
+
+#[test]
+fn leaves_images_alone_by_default() {
+    let site = make_site();
+    let config = Config::default_for_test();
+    let imageproc = Mutex::new(Processor::new(site.path().to_path_buf(), &config));
+
+    let body = render("![A photo](/images/photo.jpg)", &config, &imageproc, "blog/post/index.md");
+    assert_eq!(body, "<p><img src=\"/images/photo.jpg\" alt=\"A photo\" /></p>\n");
+}
+
+#[test]
+fn can_add_dimensions_to_local_images() {
+    let site = make_site();
+    let mut config = Config::default_for_test();
+    config.markdown.images = true;
+    let imageproc = Mutex::new(Processor::new(site.path().to_path_buf(), &config));
+
+    // In the static directory, with a title and a query string
+    let body = render(
+        "![A *photo*](/images/photo.jpg?v=2 \"Title\")",
+        &config,
+        &imageproc,
+        "blog/post/index.md",
+    );
+    assert_eq!(
+        body,
+        "<p><img src=\"/images/photo.jpg?v=2\" alt=\"A photo\" title=\"Title\" width=\"300\" \
+         height=\"380\" loading=\"lazy\" /></p>\n"
+    );
+
+    // Colocated with the page, and an SVG
+    let body =
+        render("![](diagram.png) ![](/images/logo.svg)", &config, &imageproc, "blog/post/index.md");
+    assert_eq!(
+        body,
+        "<p><img src=\"diagram.png\" alt=\"\" width=\"300\" height=\"380\" loading=\"lazy\" /> \
+         <img src=\"/images/logo.svg\" alt=\"\" width=\"200\" height=\"100\" loading=\"lazy\" /></p>\n"
+    );
+
+    // External, missing and relative to a page not in its own directory: left alone
+    let body = render(
+        "![](https://example.com/a.png) ![](/images/missing.png) ![](post/diagram.png)",
+        &config,
+        &imageproc,
+        "blog/other.md",
+    );
+    assert_eq!(
+        body,
+        "<p><img src=\"https://example.com/a.png\" alt=\"\" /> \
+         <img src=\"/images/missing.png\" alt=\"\" /> <img src=\"post/diagram.png\" alt=\"\" /></p>\n"
+    );
+    assert_eq!(imageproc.lock().unwrap().num_img_ops(), 0);
+}
+
+#[test]
+fn can_add_srcset_of_resized_images() {
+    let site = make_site();
+    let mut config = Config::default_for_test();
+    config.markdown.images = true;
+    config.markdown.image_widths = vec![100, 200, 600];
+    config.markdown.image_sizes = Some("(max-width: 600px) 100vw, 50vw".to_string());
+    let imageproc = Mutex::new(Processor::new(site.path().to_path_buf(), &config));
+
+    let body = render("![](/images/photo.jpg)", &config, &imageproc, "blog/post/index.md");
+    let expected = Regex::new(
+        r#"^<p><img src="/images/photo.jpg" alt="" width="300" height="380" loading="lazy" srcset="\S+/processed_images/[0-9a-f]+\.jpg 100w, \S+/processed_images/[0-9a-f]+\.jpg 200w, /images/photo.jpg 300w" sizes="\(max-width: 600px\) 100vw, 50vw" /></p>\n$"#,
+    )
+    .unwrap();
+    assert!(expected.is_match(&body), "{}", body);
+    assert_eq!(imageproc.lock().unwrap().num_img_ops(), 2);
+
+    // SVGs are not resized
+    let body = render("![](/images/logo.svg)", &config, &imageproc, "blog/post/index.md");
+    assert!(!body.contains("srcset"));
+    assert_eq!(imageproc.lock().unwrap().num_img_ops(), 2);
+}
+
+#[test]
+fn errors_on_invalid_images() {
+    let site = make_site();
+    let mut config = Config::default_for_test();
+    config.markdown.images = true;
+    let imageproc = Mutex::new(Processor::new(site.path().to_path_buf(), &config));
+    let permalinks = HashMap::new();
+    let mut context = RenderContext::new(
+        &ZOLA_TERA,
+        &config,
+        &config.default_language,
+        "",
+        &permalinks,
+        InsertAnchor::None,
+    );
+    context.set_imageproc(&imageproc);
+
+    let err = render_content("![](/images/broken.png)", &context).unwrap_err();
+    assert!(err.to_string().starts_with("Failed to read image: "), "{}", err);
+}
@@ -0,0 +1,114 @@
+//! Fingerprinting of the static files and compiled Sass, enabled with `fingerprint_assets`.
+//!
+//! The files matching one of the globs are written with a hash of their content in their name,
+//! eg `site.css` becomes `site.0123456789abcdef.css`, so they can be cached forever by CDNs
+//! ignoring query strings. `get_url` returns the fingerprinted path and the mapping between the
+//! original and fingerprinted paths is written to `asset-manifest.json`.
+use std::collections::HashMap;
+use std::fs::{read, rename};
+use std::path::{Path, PathBuf};
+
+use globset::GlobSet;
+use relative_path::RelativePathBuf;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use errors::{Error, Result};
+
+pub const ASSET_MANIFEST_FILENAME: &str = "asset-manifest.json";
+/// How many characters of the SHA-256 of the content go in the name
+const HASH_LENGTH: usize = 16;
+
+/// Original path -> fingerprinted path, both relative to the output directory
+pub type AssetPaths = HashMap<String, String>;
+
+/// Adds the hash of `content` to `path`, before the extension
+pub fn fingerprinted_path(path: &str, content: &[u8]) -> String {
+    let hash = format!("{:x}", Sha256::digest(content));
+    let hash = &hash[..HASH_LENGTH];
+    let (dir, filename) = match path.rfind('/') {
+        Some(i) => path.split_at(i + 1),
+        None => ("", path),
+    };
+    // Not using the extension of dotfiles like `.htaccess`
+    match filename.rfind('.').filter(|i| *i > 0) {
+        Some(i) => format!("{}{}.{}{}", dir, &filename[..i], hash, &filename[i..]),
+        None => format!("{}{}.{}", dir, filename, hash),
+    }
+}
+
+/// Finds the fingerprinted paths of the files of the static directories matching `globs`.
+/// A file in a later directory replaces the one with the same path in an earlier one, like
+/// when they are copied.
+pub fn fingerprint_static_files(static_paths: &[PathBuf], globs: &GlobSet) -> Result<AssetPaths> {
+    let mut assets = AssetPaths::new();
+    for static_path in static_paths {
+        for entry in WalkDir::new(static_path).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(static_path).unwrap();
+            // Processed images already have a hash in their name and `resize_image` returns their URL
+            if relative.starts_with(imageproc::RESIZED_SUBDIR) {
+                continue;
+            }
+            let relative = RelativePathBuf::from_path(relative).unwrap().to_string();
+            if !globs.is_match(&relative) {
+                continue;
+            }
+            let content = read(entry.path()).map_err(|e| {
+                Error::chain(format!("Failed to read {}", entry.path().display()), e)
+            })?;
+            let fingerprinted = fingerprinted_path(&relative, &content);
+            assets.insert(relative, fingerprinted);
+        }
+    }
+    Ok(assets)
+}
+
+/// Renames the given files of the output directory matching `globs` to their fingerprinted path
+pub fn fingerprint_output_files(
+    output_path: &Path,
+    files: &[PathBuf],
+    globs: &GlobSet,
+) -> Result<AssetPaths> {
+    let mut assets = AssetPaths::new();
+    for file in files {
+        let relative = RelativePathBuf::from_path(file.strip_prefix(output_path).unwrap())
+            .unwrap()
+            .to_string();
+        if !globs.is_match(&relative) {
+            continue;
+        }
+        let content = read(file)
+            .map_err(|e| Error::chain(format!("Failed to read {}", file.display()), e))?;
+        let fingerprinted = fingerprinted_path(&relative, &content);
+        move_output_file(output_path, &relative, &fingerprinted)?;
+        assets.insert(relative, fingerprinted);
+    }
+    Ok(assets)
+}
+
+/// Moves a file of the output directory to its fingerprinted path
+pub fn move_output_file(output_path: &Path, from: &str, to: &str) -> Result<()> {
+    let from = output_path.join(from);
+    rename(&from, output_path.join(to))
+        .map_err(|e| Error::chain(format!("Failed to fingerprint {}", from.display()), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fingerprinted_path;
+
+    #[test]
+    fn can_fingerprint_paths() {
+        let hash = "2cf24dba5fb0a30e";
+        assert_eq!(fingerprinted_path("site.css", b"hello"), format!("site.{}.css", hash));
+        assert_eq!(
+            fingerprinted_path("js/app.min.js", b"hello"),
+            format!("js/app.min.{}.js", hash)
+        );
+        assert_eq!(fingerprinted_path("a.b/LICENSE", b"hello"), format!("a.b/LICENSE.{}", hash));
+        assert_eq!(fingerprinted_path(".htaccess", b"hello"), format!(".htaccess.{}", hash));
+    }
+}
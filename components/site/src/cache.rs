@@ -0,0 +1,389 @@
+//! The on-disk cache used by `zola build --incremental`.
+//!
+//! It stores a fingerprint of everything a build depends on outside of the content
+//! (Zola version, config, templates, themes, Sass and the list of static files) as well as, for
+//! every rendered page and section, a hash of what was given to the template along
+//! with the output file it was written to.
+//! As long as the fingerprint matches, the output directory is kept and only the
+//! outputs whose hash changed are rendered again.
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use config::Config;
+use errors::{Error, Result};
+use library::{Library, Page, Section};
+use utils::fs::{create_file, ensure_directory_exists, read_file, remove_file_and_empty_parents};
+
+/// The default location of the cache, relative to the site root
+pub const CACHE_DIR: &str = ".zola-cache";
+const CACHE_FILENAME: &str = "build.json";
+/// Bump it whenever the format of the file or what goes in the hashes changes
+const CACHE_VERSION: u32 = 2;
+
+/// Template functions that give a template access to content other than the one being rendered.
+/// If a template calls one of them, every output depends on the whole content of the site.
+pub const LIBRARY_FNS: &[&str] = &["get_page", "get_section", "get_taxonomy", "get_taxonomy_url"];
+/// Template functions (or function arguments) whose result can change without any of the files
+/// we track changing. The outputs of templates calling one of them are always rendered.
+pub const VOLATILE_FNS: &[&str] = &[
+    "load_data",
+    "resize_image",
+    "get_image_metadata",
+    "get_file_hash",
+    "get_url.cachebust",
+    "now",
+];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    fingerprint: u64,
+    output_path: PathBuf,
+    /// Output path (relative to the output directory) -> hash of what was rendered in it
+    entries: HashMap<String, u64>,
+    /// All the files written by the build, relative to the output directory
+    outputs: Vec<String>,
+}
+
+/// Hash of all the inputs of the build that are not content
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Fingerprint(pub u64);
+
+impl Fingerprint {
+    /// Hashes all the inputs of the build that are not content.
+    /// `zola_version` is the version of Zola building the site, as another version might render
+    /// the same content differently.
+    pub fn compute(
+        base_path: &Path,
+        config_file: &Path,
+        config: &Config,
+        zola_version: &str,
+    ) -> Result<Self> {
+        let mut hasher = StableHasher::default();
+        hasher.write_u64(u64::from(CACHE_VERSION));
+        hasher.write_str(zola_version);
+        hasher.write_str(&read_file(config_file)?);
+        // The base URL can be overridden from the CLI
+        hasher.write_str(&config.base_url);
+
+        let mut roots = vec![base_path.to_path_buf()];
+        if let Some(ref theme) = config.theme {
+            let theme_path = base_path.join("themes").join(theme);
+            hasher.write_str(&read_file(&theme_path.join("theme.toml")).unwrap_or_default());
+            roots.push(theme_path);
+        }
+
+        for root in roots {
+            for entry in sorted_files(&root.join("templates")) {
+                hasher.write_path(entry.strip_prefix(&root).unwrap());
+                hasher.write(&std::fs::read(&entry)?);
+            }
+            for entry in sorted_files(&root.join("sass")) {
+                hasher.write_path(entry.strip_prefix(&root).unwrap());
+                hasher.write(&std::fs::read(&entry)?);
+            }
+            // Static files are copied only if they changed so we only care about files
+            // being added or removed, unless their hash ends up in the URLs
+            for entry in sorted_files(&root.join("static")) {
+                hasher.write_path(entry.strip_prefix(&root).unwrap());
+                if config.fingerprint_assets_globset.is_some() {
+                    hasher.write(&std::fs::read(&entry)?);
+                }
+            }
+        }
+
+        Ok(Fingerprint(hasher.finish()))
+    }
+}
+
+/// The hasher of everything stored in the cache.
+/// Unlike `DefaultHasher`, it gives the same hashes with every build of Zola since they are
+/// compared to the ones of the previous build.
+#[derive(Default)]
+struct StableHasher(Sha256);
+
+impl StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        // Prefixed with their length so consecutive values can't be confused
+        self.write_u64(bytes.len() as u64);
+        self.0.update(bytes);
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+    }
+
+    fn write_path(&mut self, path: &Path) {
+        self.write_str(&path.to_string_lossy());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0.update(n.to_le_bytes());
+    }
+
+    fn finish(self) -> u64 {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(&self.0.finalize()[..8]);
+        u64::from_le_bytes(bytes)
+    }
+}
+
+fn sorted_files(path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+#[derive(Debug)]
+pub struct BuildCache {
+    path: PathBuf,
+    output_path: PathBuf,
+    fingerprint: Fingerprint,
+    /// Hash of the whole content, only set if a template can access it
+    library_hash: Option<u64>,
+    /// Hash of all the permalinks, as internal links and `get_url` resolve to them
+    permalinks_hash: u64,
+    /// Whether the previous build can be reused at all
+    valid: bool,
+    previous: CacheFile,
+    entries: Mutex<HashMap<String, u64>>,
+    outputs: Mutex<HashSet<String>>,
+}
+
+impl BuildCache {
+    /// Loads the cache stored at `path`, if any.
+    /// A cache from a different version of the cache or with a different fingerprint is ignored.
+    pub fn load(path: &Path, output_path: &Path, fingerprint: Fingerprint) -> BuildCache {
+        let previous = read_file(&path.join(CACHE_FILENAME))
+            .ok()
+            .and_then(|c| serde_json::from_str::<CacheFile>(&c).ok())
+            .unwrap_or_default();
+        let valid = previous.version == CACHE_VERSION
+            && previous.fingerprint == fingerprint.0
+            && previous.output_path == output_path
+            && output_path.exists();
+
+        BuildCache {
+            path: path.to_path_buf(),
+            output_path: output_path.to_path_buf(),
+            fingerprint,
+            library_hash: None,
+            permalinks_hash: 0,
+            valid,
+            previous,
+            entries: Mutex::new(HashMap::new()),
+            outputs: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Whether the output directory of the previous build can be kept
+    pub fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    /// Needs to be called once the content is loaded.
+    /// `uses_library` is whether any template can access content other than the one it renders,
+    /// in which case every output depends on the whole content.
+    pub fn set_library(
+        &mut self,
+        library: &Library,
+        permalinks: &HashMap<String, String>,
+        uses_library: bool,
+    ) {
+        let mut hasher = StableHasher::default();
+        let mut permalinks: Vec<_> = permalinks.iter().collect();
+        permalinks.sort();
+        hasher.write_u64(permalinks.len() as u64);
+        for (key, permalink) in permalinks {
+            hasher.write_str(key);
+            hasher.write_str(permalink);
+        }
+        self.permalinks_hash = hasher.finish();
+
+        if !uses_library {
+            return;
+        }
+        let mut hasher = StableHasher::default();
+        let mut pages = library.pages_values();
+        pages.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+        for page in pages {
+            hash_serialized(&page.to_serialized_basic(library), &mut hasher);
+        }
+        let mut sections = library.sections_values();
+        sections.sort_by(|a, b| a.file.path.cmp(&b.file.path));
+        for section in sections {
+            hash_serialized(&section.to_serialized_basic(library), &mut hasher);
+        }
+        self.library_hash = Some(hasher.finish());
+    }
+
+    /// Hash of everything the template of that page gets
+    pub fn hash_page(&self, page: &Page, library: &Library) -> u64 {
+        let mut hasher = self.output_hasher();
+        hasher.write_str(page.meta.template.as_deref().unwrap_or_default());
+        hash_serialized(&page.to_serialized(library), &mut hasher);
+        hasher.finish()
+    }
+
+    /// Hash of everything the template of that section gets
+    pub fn hash_section(&self, section: &Section, library: &Library) -> u64 {
+        let mut hasher = self.output_hasher();
+        hasher.write_str(section.get_template_name());
+        hash_serialized(&section.to_serialized(library), &mut hasher);
+        hasher.finish()
+    }
+
+    /// A hasher already given what every output depends on
+    fn output_hasher(&self) -> StableHasher {
+        let mut hasher = StableHasher::default();
+        hasher.write_u64(self.permalinks_hash);
+        match self.library_hash {
+            Some(library_hash) => {
+                hasher.write_u64(1);
+                hasher.write_u64(library_hash);
+            }
+            None => hasher.write_u64(0),
+        }
+        hasher
+    }
+
+    /// Returns whether the output at `output` was rendered from the same data in the previous
+    /// build and is still there, in which case it doesn't need to be rendered again.
+    /// The hash is remembered for the next build either way.
+    pub fn is_fresh(&self, output: &str, hash: u64) -> bool {
+        self.entries.lock().unwrap().insert(output.to_string(), hash);
+        let fresh = self.valid
+            && self.previous.entries.get(output) == Some(&hash)
+            && self.output_path.join(output).exists();
+        if fresh {
+            self.record_output(output);
+        }
+        fresh
+    }
+
+    /// Marks a file, relative to the output directory, as being part of the current build
+    pub fn record_output(&self, output: &str) {
+        self.outputs.lock().unwrap().insert(output.to_string());
+    }
+
+    /// Deletes the files written by the previous build that were not written this time,
+    /// for example because the page they came from was deleted
+    pub fn remove_stale_outputs(&self) -> Result<()> {
+        if !self.valid {
+            return Ok(());
+        }
+
+        let outputs = self.outputs.lock().unwrap();
+        for output in self.previous.outputs.iter().filter(|o| !outputs.contains(*o)) {
+            let path = self.output_path.join(output);
+            if !path.exists() {
+                continue;
+            }
+            remove_file_and_empty_parents(&path, &self.output_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the cache to disk for the next build
+    pub fn save(&self) -> Result<()> {
+        let mut outputs: Vec<_> = self.outputs.lock().unwrap().iter().cloned().collect();
+        outputs.sort();
+        let cache = CacheFile {
+            version: CACHE_VERSION,
+            fingerprint: self.fingerprint.0,
+            output_path: self.output_path.clone(),
+            entries: self.entries.lock().unwrap().clone(),
+            outputs,
+        };
+        ensure_directory_exists(&self.path)?;
+        let content = serde_json::to_string(&cache)
+            .map_err(|e| Error::chain("Failed to serialize the build cache", e))?;
+        create_file(&self.path.join(CACHE_FILENAME), &content)
+    }
+}
+
+/// Hashes a serializable value through its JSON representation.
+/// Object keys are sorted first so the hash doesn't depend on the iteration order of the
+/// maps it came from.
+fn hash_serialized<T: serde::Serialize>(value: &T, hasher: &mut StableHasher) {
+    let value = serde_json::to_value(value).expect("Failed to serialize value to hash");
+    hash_json(&value, hasher);
+}
+
+/// Every value starts with a tag for its type so, for example, `null` and `"null"` differ
+fn hash_json(value: &Value, hasher: &mut StableHasher) {
+    match value {
+        Value::Null => hasher.write(b"n"),
+        Value::Bool(b) => hasher.write(if *b { b"t" } else { b"f" }),
+        Value::Number(n) => {
+            hasher.write(b"#");
+            hasher.write_str(&n.to_string());
+        }
+        Value::String(s) => {
+            hasher.write(b"s");
+            hasher.write_str(s);
+        }
+        Value::Array(items) => {
+            hasher.write(b"a");
+            hasher.write_u64(items.len() as u64);
+            for item in items {
+                hash_json(item, hasher);
+            }
+        }
+        Value::Object(map) => {
+            hasher.write(b"o");
+            let mut keys: Vec<_> = map.keys().collect();
+            keys.sort();
+            hasher.write_u64(keys.len() as u64);
+            for key in keys {
+                hasher.write_str(key);
+                hash_json(&map[key], hasher);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{hash_json, StableHasher};
+
+    fn hash(value: &serde_json::Value) -> u64 {
+        let mut hasher = StableHasher::default();
+        hash_json(value, &mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn hash_doesnt_depend_on_key_order() {
+        let a: serde_json::Value = serde_json::from_str(r#"{"a": 1, "b": [true, "c"]}"#).unwrap();
+        let b: serde_json::Value = serde_json::from_str(r#"{"b": [true, "c"], "a": 1}"#).unwrap();
+        assert_eq!(hash(&a), hash(&b));
+    }
+
+    #[test]
+    fn hash_is_stable() {
+        // The hashes of the previous build are compared to the ones of the current build, which
+        // can be done by another build of Zola: changing them needs a new `CACHE_VERSION`
+        assert_eq!(hash(&json!({"a": [1, null, true, "b"]})), 12194060586166012335);
+    }
+
+    #[test]
+    fn hash_changes_with_values() {
+        assert_ne!(hash(&json!({"a": 1})), hash(&json!({"a": 2})));
+        assert_ne!(hash(&json!(["a", "b"])), hash(&json!(["b", "a"])));
+        assert_ne!(hash(&json!({"a": null})), hash(&json!({"b": null})));
+    }
+}
@@ -0,0 +1,231 @@
+//! Pre-compression of the generated files, as configured in the `compress_output` section of
+//! the config.
+//!
+//! `zola build` writes a `.gz` and/or `.br` file next to every text file big enough to be worth
+//! it, for web servers able to serve them directly. `zola serve` compresses the responses instead,
+//! with a faster level as it does it on every request.
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use relative_path::RelativePathBuf;
+use walkdir::WalkDir;
+
+use config::CompressOutput;
+use errors::{Error, Result};
+use utils::fs::file_stale;
+
+/// The extensions of the files worth compressing, everything else is likely already compressed
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "xml", "css", "js", "json", "svg"];
+
+/// How much to compress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    /// The smallest output, for the files written once by `zola build`
+    Best,
+    /// Fast enough for `zola serve` to compress every response
+    Fast,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// The name used in the `Accept-Encoding` and `Content-Encoding` headers
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// The extension added to the name of the pre-compressed file
+    pub fn extension(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gz",
+        }
+    }
+
+    pub fn compress(self, content: &[u8], level: Level) -> Result<Vec<u8>> {
+        let mut compressed = Vec::new();
+        match self {
+            Encoding::Brotli => {
+                let quality = match level {
+                    Level::Best => 11,
+                    Level::Fast => 4,
+                };
+                let mut writer = brotli::CompressorWriter::new(&mut compressed, 4096, quality, 22);
+                writer.write_all(content)?;
+            }
+            Encoding::Gzip => {
+                let compression = match level {
+                    Level::Best => flate2::Compression::best(),
+                    Level::Fast => flate2::Compression::fast(),
+                };
+                let mut encoder = GzEncoder::new(&mut compressed, compression);
+                encoder.write_all(content)?;
+                encoder.finish()?;
+            }
+        }
+        Ok(compressed)
+    }
+}
+
+/// Decides what gets compressed and how
+#[derive(Clone, Debug)]
+pub struct Compressor {
+    /// In order of preference
+    encodings: Vec<Encoding>,
+    min_size: u64,
+}
+
+impl Compressor {
+    /// Returns `None` if compression is not enabled in the config
+    pub fn new(config: &CompressOutput) -> Option<Compressor> {
+        if !config.is_enabled() {
+            return None;
+        }
+        let mut encodings = Vec::new();
+        if config.brotli {
+            encodings.push(Encoding::Brotli);
+        }
+        if config.gzip {
+            encodings.push(Encoding::Gzip);
+        }
+        Some(Compressor { encodings, min_size: config.min_size })
+    }
+
+    /// Whether the file at `path` should be compressed, given its size in bytes
+    pub fn should_compress(&self, path: &Path, size: u64) -> bool {
+        size >= self.min_size
+            && path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map_or(false, |e| COMPRESSIBLE_EXTENSIONS.contains(&e))
+    }
+
+    /// Picks the encoding to use for a request sent with the given `Accept-Encoding` header
+    pub fn negotiate(&self, accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<&str> = accept_encoding
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(str::trim);
+                let name = parts.next()?;
+                // `q=0` means the encoding is not acceptable
+                let refused = parts
+                    .any(|p| p.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0));
+                if refused {
+                    None
+                } else {
+                    Some(name)
+                }
+            })
+            .collect();
+        self.encodings
+            .iter()
+            .copied()
+            .find(|e| accepted.iter().any(|a| a.eq_ignore_ascii_case(e.name()) || *a == "*"))
+    }
+
+    /// Writes the compressed variants of all the files worth compressing in `output_path`,
+    /// unless they are already up to date. Returns the paths of all the compressed files,
+    /// relative to `output_path`.
+    pub fn compress_directory(&self, output_path: &Path) -> Result<Vec<RelativePathBuf>> {
+        let mut compressed_files = Vec::new();
+        for entry in WalkDir::new(output_path).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+            if !self.should_compress(path, size) {
+                continue;
+            }
+
+            let mut content = None;
+            for encoding in &self.encodings {
+                let mut compressed_path = path.as_os_str().to_owned();
+                compressed_path.push(".");
+                compressed_path.push(encoding.extension());
+                let compressed_path = Path::new(&compressed_path);
+                if file_stale(path, compressed_path) {
+                    if content.is_none() {
+                        content = Some(std::fs::read(path).map_err(|e| {
+                            Error::chain(format!("Failed to read {}", path.display()), e)
+                        })?);
+                    }
+                    let compressed = encoding.compress(content.as_ref().unwrap(), Level::Best)?;
+                    std::fs::write(compressed_path, compressed).map_err(|e| {
+                        Error::chain(format!("Failed to write {}", compressed_path.display()), e)
+                    })?;
+                }
+                let relative = compressed_path.strip_prefix(output_path).unwrap();
+                compressed_files.push(RelativePathBuf::from_path(relative).unwrap());
+            }
+        }
+        Ok(compressed_files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn compressor() -> Compressor {
+        Compressor::new(&CompressOutput { gzip: true, brotli: true, min_size: 10 }).unwrap()
+    }
+
+    #[test]
+    fn is_disabled_by_default() {
+        assert!(Compressor::new(&CompressOutput::default()).is_none());
+    }
+
+    #[test]
+    fn only_compresses_big_text_files() {
+        let compressor = compressor();
+        assert!(compressor.should_compress(Path::new("index.html"), 10));
+        assert!(compressor.should_compress(Path::new("search_index.en.js"), 100));
+        assert!(!compressor.should_compress(Path::new("index.html"), 9));
+        assert!(!compressor.should_compress(Path::new("image.png"), 100));
+        assert!(!compressor.should_compress(Path::new("index.html.gz"), 100));
+    }
+
+    #[test]
+    fn can_negotiate_encoding() {
+        let compressor = compressor();
+        assert_eq!(compressor.negotiate("gzip, deflate, br"), Some(Encoding::Brotli));
+        assert_eq!(compressor.negotiate("gzip, br;q=0"), Some(Encoding::Gzip));
+        assert_eq!(compressor.negotiate("GZIP"), Some(Encoding::Gzip));
+        assert_eq!(compressor.negotiate("*"), Some(Encoding::Brotli));
+        assert_eq!(compressor.negotiate("deflate"), None);
+        assert_eq!(compressor.negotiate(""), None);
+
+        let gzip_only =
+            Compressor::new(&CompressOutput { gzip: true, brotli: false, min_size: 10 }).unwrap();
+        assert_eq!(gzip_only.negotiate("br"), None);
+    }
+
+    #[test]
+    fn can_compress() {
+        let content = b"<p>Hello world</p>".repeat(10);
+        for level in &[Level::Best, Level::Fast] {
+            let compressed = Encoding::Gzip.compress(&content, *level).unwrap();
+            let mut decompressed = Vec::new();
+            flate2::read::GzDecoder::new(&compressed[..]).read_to_end(&mut decompressed).unwrap();
+            assert_eq!(decompressed, content);
+
+            let compressed = Encoding::Brotli.compress(&content, *level).unwrap();
+            let mut decompressed = Vec::new();
+            brotli::Decompressor::new(&compressed[..], 4096)
+                .read_to_end(&mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, content);
+        }
+    }
+}
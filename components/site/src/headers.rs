@@ -0,0 +1,331 @@
+//! The response headers and redirects of the site, from the `headers` and `redirects` of the
+//! config and the Netlify-style `_headers` and `_redirects` files of the static directories.
+//!
+//! `zola serve` applies them so issues like a too strict Content-Security-Policy show up before
+//! deploying, and `zola build` can write the ones of the config to the `_headers` and
+//! `_redirects` files of the output directory.
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use config::{Config, HeaderRule, Redirect};
+use errors::{bail, Result};
+use utils::fs::read_file;
+
+pub const HEADERS_FILENAME: &str = "_headers";
+pub const REDIRECTS_FILENAME: &str = "_redirects";
+
+#[derive(Clone, Debug, Default)]
+pub struct ResponseRules {
+    headers: Vec<HeaderRule>,
+    redirects: Vec<Redirect>,
+}
+
+impl ResponseRules {
+    /// Loads the `_headers` and `_redirects` files found in `static_paths`, the last one winning
+    /// like when they are copied, followed by the rules of the config
+    pub fn load(config: &Config, static_paths: &[PathBuf]) -> Result<ResponseRules> {
+        let mut headers = match read_static_file(static_paths, HEADERS_FILENAME) {
+            Some(content) => parse_headers_file(&content)?,
+            None => Vec::new(),
+        };
+        headers.extend(config.headers.iter().cloned());
+        for rule in &headers {
+            for (name, value) in &rule.values {
+                if !is_valid_header(name, value) {
+                    bail!("Invalid header `{}: {}` for `{}`", name, value, rule.path);
+                }
+            }
+        }
+
+        let mut redirects = match read_static_file(static_paths, REDIRECTS_FILENAME) {
+            Some(content) => parse_redirects_file(&content)?,
+            None => Vec::new(),
+        };
+        redirects.extend(config.redirects.iter().cloned());
+
+        Ok(ResponseRules { headers, redirects })
+    }
+
+    /// The headers to send for `path`, from all the rules matching it.
+    /// A header set by several rules gets the value of the last one.
+    pub fn headers(&self, path: &str) -> Vec<(&str, &str)> {
+        let mut headers: Vec<(&str, &str)> = Vec::new();
+        for rule in self.headers.iter().filter(|r| match_path(&r.path, path).is_some()) {
+            for (name, value) in &rule.values {
+                headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+                headers.push((name, value));
+            }
+        }
+        headers
+    }
+
+    /// The first redirect matching `path` if any, along with its destination
+    pub fn redirect(&self, path: &str) -> Option<(&Redirect, String)> {
+        self.redirects.iter().find_map(|redirect| {
+            let captures = match_path(&redirect.from, path)?;
+            Some((redirect, fill_placeholders(&redirect.to, captures)))
+        })
+    }
+}
+
+/// The content of the last static directory having that file, if any
+pub fn read_static_file(static_paths: &[PathBuf], filename: &str) -> Option<String> {
+    static_paths.iter().rev().find_map(|p| read_file(&p.join(filename)).ok())
+}
+
+/// Header names are tokens, and values can't contain control characters
+fn is_valid_header(name: &str, value: &str) -> bool {
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+        && value.chars().all(|c| c == '\t' || !c.is_control())
+}
+
+/// Parses a Netlify `_headers` file: a path followed by indented `Name: value` lines
+pub fn parse_headers_file(content: &str) -> Result<Vec<HeaderRule>> {
+    let mut rules: Vec<HeaderRule> = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            rules.push(HeaderRule { path: trimmed.to_string(), values: BTreeMap::new() });
+            continue;
+        }
+
+        let rule = match rules.last_mut() {
+            Some(rule) => rule,
+            None => bail!("Line {} of `{}` is a header without a path", i + 1, HEADERS_FILENAME),
+        };
+        let (name, value) = match trimmed.split_once(':') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => bail!("Line {} of `{}` is not a `Name: value` header", i + 1, HEADERS_FILENAME),
+        };
+        // Like Netlify, a header given several times gets all the values
+        rule.values
+            .entry(name.to_string())
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    Ok(rules)
+}
+
+/// Parses a Netlify `_redirects` file: `from to [status][!]` lines.
+/// The rules with conditions, like `Country=fr`, can't be applied locally and are skipped.
+pub fn parse_redirects_file(content: &str) -> Result<Vec<Redirect>> {
+    let mut redirects = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        let (from, to) = match parts[..] {
+            [from, to, ..] => (from, to),
+            _ => bail!("Line {} of `{}` needs a path and a destination", i + 1, REDIRECTS_FILENAME),
+        };
+        if parts.len() > 3 {
+            continue;
+        }
+        let (status, force) = match parts.get(2) {
+            Some(status) => {
+                let force = status.ends_with('!');
+                match status.trim_end_matches('!').parse() {
+                    Ok(status) => (status, force),
+                    Err(_) => bail!(
+                        "Line {} of `{}` has an invalid status `{}`",
+                        i + 1,
+                        REDIRECTS_FILENAME,
+                        status
+                    ),
+                }
+            }
+            None => (301, false),
+        };
+        redirects.push(Redirect { from: from.to_string(), to: to.to_string(), status, force });
+    }
+    Ok(redirects)
+}
+
+/// Writes header rules in the format of a Netlify `_headers` file
+pub fn headers_file(rules: &[HeaderRule]) -> String {
+    let mut content = String::new();
+    for rule in rules {
+        content.push_str(&rule.path);
+        content.push('\n');
+        for (name, value) in &rule.values {
+            content.push_str(&format!("  {}: {}\n", name, value));
+        }
+    }
+    content
+}
+
+/// Writes redirects in the format of a Netlify `_redirects` file
+pub fn redirects_file(redirects: &[Redirect]) -> String {
+    let mut content = String::new();
+    for redirect in redirects {
+        let force = if redirect.force { "!" } else { "" };
+        content
+            .push_str(&format!("{} {} {}{}\n", redirect.from, redirect.to, redirect.status, force));
+    }
+    content
+}
+
+/// `/a/` and `/a` are the same path
+fn trim_trailing_slash(path: &str) -> &str {
+    if path.len() > 1 {
+        path.trim_end_matches('/')
+    } else {
+        path
+    }
+}
+
+/// Matches `path` against a pattern of `_headers`/`_redirects`, returning the values of its
+/// `:placeholder` segments. A `*` at the end of the pattern matches anything, available as `splat`.
+fn match_path<'a>(pattern: &'a str, path: &'a str) -> Option<Vec<(&'a str, &'a str)>> {
+    let pattern: Vec<&str> = trim_trailing_slash(pattern).split('/').collect();
+    let path = trim_trailing_slash(path);
+    let segments: Vec<&str> = path.split('/').collect();
+    let mut captures = Vec::new();
+
+    for (i, part) in pattern.iter().enumerate() {
+        if let Some(prefix) = part.strip_suffix('*').filter(|_| i == pattern.len() - 1) {
+            // Everything after the segments matched so far
+            let offset: usize = segments.iter().take(i).map(|s| s.len() + 1).sum();
+            let rest = path.get(offset..).unwrap_or("");
+            let splat = rest.strip_prefix(prefix)?;
+            captures.push(("splat", splat));
+            return Some(captures);
+        }
+
+        let segment = segments.get(i)?;
+        match part.strip_prefix(':') {
+            Some(name) if !segment.is_empty() => captures.push((name, *segment)),
+            Some(_) => return None,
+            None if part != segment => return None,
+            None => (),
+        }
+    }
+
+    if segments.len() == pattern.len() {
+        Some(captures)
+    } else {
+        None
+    }
+}
+
+/// Replaces the `:splat` and `:placeholder` of a redirect destination by their values
+fn fill_placeholders(to: &str, mut captures: Vec<(&str, &str)>) -> String {
+    // Longest names first so `:page` doesn't replace the start of `:pages`
+    captures.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    let mut to = to.to_string();
+    for (name, value) in captures {
+        to = to.replace(&format!(":{}", name), value);
+    }
+    to
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_match_paths() {
+        assert_eq!(match_path("/about", "/about/"), Some(vec![]));
+        assert_eq!(match_path("/about/", "/about"), Some(vec![]));
+        assert_eq!(match_path("/about", "/contact"), None);
+        assert_eq!(match_path("/about", "/about/team"), None);
+        assert_eq!(match_path("/*", "/"), Some(vec![("splat", "")]));
+        assert_eq!(match_path("/*", "/a/b.css"), Some(vec![("splat", "a/b.css")]));
+        assert_eq!(match_path("/news/*", "/news/2021/hello"), Some(vec![("splat", "2021/hello")]));
+        assert_eq!(match_path("/news/*", "/blog/2021"), None);
+        assert_eq!(match_path("/img*", "/img-large/a.png"), Some(vec![("splat", "-large/a.png")]));
+        assert_eq!(
+            match_path("/blog/:year/:slug", "/blog/2021/hello"),
+            Some(vec![("year", "2021"), ("slug", "hello")])
+        );
+        assert_eq!(match_path("/blog/:year/:slug", "/blog/2021"), None);
+    }
+
+    #[test]
+    fn can_parse_headers_file() {
+        let content = "
+# Everything
+/*
+  X-Frame-Options: DENY
+  Link: </style.css>; rel=preload
+  Link: </app.js>; rel=preload
+
+/fonts/*
+  Cache-Control: public, max-age=31536000
+";
+        let rules = parse_headers_file(content).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].path, "/*");
+        assert_eq!(rules[0].values["X-Frame-Options"], "DENY");
+        assert_eq!(rules[0].values["Link"], "</style.css>; rel=preload, </app.js>; rel=preload");
+        assert_eq!(rules[1].values["Cache-Control"], "public, max-age=31536000");
+        assert_eq!(parse_headers_file(&headers_file(&rules)).unwrap(), rules);
+
+        assert!(parse_headers_file("  X-Frame-Options: DENY").is_err());
+        assert!(parse_headers_file("/*\n  X-Frame-Options").is_err());
+    }
+
+    #[test]
+    fn can_parse_redirects_file() {
+        let content = "
+/home /
+/blog/* /news/:splat 302
+/app/* /app/index.html 200!
+/ /fr 302 Language=fr
+";
+        let redirects = parse_redirects_file(content).unwrap();
+        assert_eq!(redirects.len(), 3);
+        assert_eq!(
+            redirects[0],
+            Redirect { from: "/home".to_string(), to: "/".to_string(), status: 301, force: false }
+        );
+        assert_eq!(redirects[1].status, 302);
+        assert_eq!(redirects[2].status, 200);
+        assert!(redirects[2].force);
+        assert_eq!(parse_redirects_file(&redirects_file(&redirects)).unwrap(), redirects);
+
+        assert!(parse_redirects_file("/home").is_err());
+        assert!(parse_redirects_file("/home / permanent").is_err());
+    }
+
+    #[test]
+    fn can_find_headers_and_redirects() {
+        let mut config = Config::default();
+        config.headers = parse_headers_file(
+            "/*\n  X-Frame-Options: DENY\n  Cache-Control: no-cache\n/fonts/*\n  cache-control: max-age=60",
+        )
+        .unwrap();
+        config.redirects = parse_redirects_file(
+            "/blog/:year/:slug /posts/:slug-:year\n/docs/* /documentation/:splat",
+        )
+        .unwrap();
+        let rules = ResponseRules::load(&config, &[]).unwrap();
+
+        assert_eq!(
+            rules.headers("/index.html"),
+            vec![("Cache-Control", "no-cache"), ("X-Frame-Options", "DENY")]
+        );
+        assert_eq!(
+            rules.headers("/fonts/a.woff2"),
+            vec![("X-Frame-Options", "DENY"), ("cache-control", "max-age=60")]
+        );
+        assert_eq!(rules.redirect("/blog/2021/hello").unwrap().1, "/posts/hello-2021");
+        assert_eq!(
+            rules.redirect("/docs/getting-started/").unwrap().1,
+            "/documentation/getting-started"
+        );
+        assert!(rules.redirect("/about").is_none());
+
+        config.headers[0].values.insert("X-Bad".to_string(), "a\nb".to_string());
+        assert!(ResponseRules::load(&config, &[]).is_err());
+    }
+}
@@ -1,36 +1,54 @@
+pub mod assets;
+pub mod cache;
+pub mod compress;
 pub mod feed;
+pub mod headers;
 pub mod link_checking;
+pub mod post_process;
+pub mod report;
 pub mod sass;
 pub mod sitemap;
+pub mod sync;
 pub mod tpls;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::remove_dir_all;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, RwLock};
 
+use chrono::{Local, NaiveDateTime, TimeZone};
+use globset::GlobSet;
 use lazy_static::lazy_static;
 use rayon::prelude::*;
+use relative_path::RelativePath;
 use tera::{Context, Tera};
 use walkdir::{DirEntry, WalkDir};
 
+use assets::AssetPaths;
+use cache::{BuildCache, Fingerprint, LIBRARY_FNS, VOLATILE_FNS};
+use compress::Compressor;
 use config::{get_config, Config};
 use errors::{bail, Error, Result};
-use front_matter::InsertAnchor;
+use front_matter::{parse_local_datetime, InsertAnchor};
+use headers::{ResponseRules, HEADERS_FILENAME, REDIRECTS_FILENAME};
 use library::{find_taxonomies, Library, Page, Paginator, Section, Taxonomy};
 use relative_path::RelativePathBuf;
+use report::{BuildReport, Counts};
 use std::time::Instant;
+use templates::dependencies::{templates_from_file, TemplateDependencies};
 use templates::{load_tera, render_redirect_template};
 use utils::fs::{
     copy_directory, copy_file_if_needed, create_directory, create_file, ensure_directory_exists,
 };
 use utils::minify;
 use utils::net::get_available_port;
-use utils::templates::{render_template, ShortcodeDefinition};
+use utils::templates::{check_template_fallbacks, render_template, ShortcodeDefinition};
 
 lazy_static! {
     /// The in-memory rendered map content
     pub static ref SITE_CONTENT: Arc<RwLock<HashMap<RelativePathBuf, String>>> = Arc::new(RwLock::new(HashMap::new()));
+    /// The in-memory redirects: path -> permalink to redirect to
+    pub static ref SITE_REDIRECTS: Arc<RwLock<HashMap<RelativePathBuf, String>>> = Arc::new(RwLock::new(HashMap::new()));
 }
 
 /// Where are we building the site
@@ -63,8 +81,30 @@ pub struct Site {
     pub library: Arc<RwLock<Library>>,
     /// Whether to load draft pages
     include_drafts: bool,
+    /// Only the pages published at that date according to their `publish_date` and `expiry_date`
+    /// are loaded. The current date if not set
+    as_of: Option<NaiveDateTime>,
+    /// If not empty, only the content in those paths (and the sections above them) is loaded
+    only_paths: Vec<PathBuf>,
+    /// For reproducible builds, the Unix timestamp returned by `now()` in templates
+    reproducible_timestamp: Option<i64>,
     build_mode: BuildMode,
     shortcode_definitions: HashMap<String, ShortcodeDefinition>,
+    /// Which templates each template needs, to know what to render again when one changes
+    template_dependencies: TemplateDependencies,
+    config_file: PathBuf,
+    /// Where to store the build cache and the version of Zola building the site, if incremental
+    /// builds are enabled
+    cache_path: Option<(PathBuf, String)>,
+    /// The cache of the current build, only set while building
+    build_cache: RwLock<Option<BuildCache>>,
+    /// Collects timings and statistics of the build if a report was asked for
+    report: Option<BuildReport>,
+    /// When syncing, the site is built in `output_path` and then copied to `sync.0`,
+    /// keeping track of what was copied in the manifest at `sync.1`
+    sync: Option<(PathBuf, PathBuf)>,
+    /// The static files and compiled Sass written under a fingerprinted name
+    assets: Arc<RwLock<AssetPaths>>,
 }
 
 impl Site {
@@ -82,6 +122,7 @@ impl Site {
 
         let tera = load_tera(path, &config)?;
         let shortcode_definitions = utils::templates::get_shortcodes(&tera);
+        let template_dependencies = TemplateDependencies::new(&tera);
 
         let content_path = path.join("content");
         let static_path = path.join("static");
@@ -100,10 +141,20 @@ impl Site {
             taxonomies: Vec::new(),
             permalinks: HashMap::new(),
             include_drafts: false,
+            as_of: None,
+            only_paths: Vec::new(),
+            reproducible_timestamp: None,
             // We will allocate it properly later on
             library: Arc::new(RwLock::new(Library::new(0, 0, false))),
             build_mode: BuildMode::Disk,
             shortcode_definitions,
+            template_dependencies,
+            config_file: config_file.to_path_buf(),
+            cache_path: None,
+            build_cache: RwLock::new(None),
+            report: None,
+            sync: None,
+            assets: Arc::new(RwLock::new(AssetPaths::new())),
         };
 
         Ok(site)
@@ -112,6 +163,7 @@ impl Site {
     /// Enable some `zola serve` related options
     pub fn enable_serve_mode(&mut self) {
         SITE_CONTENT.write().unwrap().clear();
+        SITE_REDIRECTS.write().unwrap().clear();
         self.config.enable_serve_mode();
         self.build_mode = BuildMode::Memory;
     }
@@ -122,6 +174,86 @@ impl Site {
         self.include_drafts = true;
     }
 
+    /// Load the site as it will be at the given date, in one of the formats of the front matter
+    /// dates: the pages are loaded according to their `publish_date` and `expiry_date` at that date.
+    /// Needs to be called before loading it
+    pub fn set_as_of(&mut self, date: &str) -> Result<()> {
+        match parse_local_datetime(date) {
+            Some(datetime) => self.as_of = Some(datetime),
+            None => bail!("`{}` is not a valid date", date),
+        }
+        Ok(())
+    }
+
+    /// Whether the page should be loaded, according to whether it is a draft and
+    /// to its publish and expiry dates
+    fn should_load_page(&self, page: &Page) -> bool {
+        let now = match (self.as_of, self.reproducible_timestamp) {
+            (Some(as_of), _) => as_of,
+            // The output of a reproducible build can't depend on when it is done
+            (None, Some(timestamp)) => Local.timestamp(timestamp, 0).naive_local(),
+            (None, None) => Local::now().naive_local(),
+        };
+        (!page.meta.draft || self.include_drafts) && page.meta.is_published_at(now)
+    }
+
+    /// Only load and render the content found in the given paths, relative to the site root,
+    /// as well as the sections containing them.
+    /// Needs to be called before loading it
+    pub fn set_only_paths<P: AsRef<Path>>(&mut self, paths: &[P]) -> Result<()> {
+        let mut only_paths = Vec::new();
+        let content_path = self.content_path.canonicalize()?;
+        for path in paths {
+            let full_path = self.base_path.join(path);
+            if !full_path.exists() {
+                bail!("`{}` does not exist", path.as_ref().display());
+            }
+            // Resolving `..` and symlinks so they can't lead out of the content
+            let relative_path = match full_path.canonicalize()?.strip_prefix(&content_path) {
+                Ok(relative_path) => relative_path.to_path_buf(),
+                Err(_) => bail!("`{}` is not in the `content` directory", path.as_ref().display()),
+            };
+            only_paths.push(self.content_path.join(relative_path));
+        }
+        self.only_paths = only_paths;
+        Ok(())
+    }
+
+    /// Whether only part of the content is loaded, see `set_only_paths`
+    pub fn is_partial_build(&self) -> bool {
+        !self.only_paths.is_empty()
+    }
+
+    /// Make the output only depend on the input: `now()` in templates always returns the
+    /// given Unix timestamp instead of the current time.
+    /// Needs to be called before loading it
+    pub fn set_reproducible(&mut self, timestamp: i64) {
+        self.reproducible_timestamp = Some(timestamp);
+    }
+
+    /// The time returned by `now()` in templates if the build is reproducible
+    pub fn reproducible_timestamp(&self) -> Option<i64> {
+        self.reproducible_timestamp
+    }
+
+    /// Only render what changed since the last build, keeping track of it in a cache
+    /// stored in the given directory.
+    /// The cache is not reused by another `zola_version` than the one that wrote it.
+    /// Only used with `zola build`, has no effect in serve mode.
+    pub fn enable_build_cache<P: AsRef<Path>>(&mut self, path: P, zola_version: &str) {
+        self.cache_path = Some((path.as_ref().to_path_buf(), zola_version.to_string()));
+    }
+
+    /// Collect timings and statistics during the build, see `build_report`
+    pub fn enable_build_report(&mut self) {
+        self.report = Some(BuildReport::default());
+    }
+
+    /// The report of the last build, if `enable_build_report` was called
+    pub fn build_report(&self) -> Option<&BuildReport> {
+        self.report.as_ref()
+    }
+
     /// The index sections are ALWAYS at those paths
     /// There are one index section for the default language + 1 per language
     fn index_section_paths(&self) -> Vec<(PathBuf, Option<&str>)> {
@@ -144,11 +276,106 @@ impl Site {
         self.live_reload = Some(live_reload_port);
     }
 
-    /// Reloads the templates and rebuild the site without re-rendering the Markdown.
-    pub fn reload_templates(&mut self) -> Result<()> {
+    /// Reloads the templates and renders again only what uses the template file at `path`.
+    /// The Markdown is only rendered again if a shortcode, the anchor link or the admonition
+    /// template changed, and only for the pages and sections using it.
+    pub fn reload_templates(&mut self, path: &Path) -> Result<()> {
         self.tera.full_reload()?;
-        // TODO: be smarter than that, no need to recompile sass for example
-        self.build()
+        self.shortcode_definitions = utils::templates::get_shortcodes(&self.tera);
+        self.template_dependencies = TemplateDependencies::new(&self.tera);
+
+        let changed = templates_from_file(&self.tera, path);
+        // The template was deleted or renamed, we can't know what was using it
+        if changed.is_empty() {
+            return self.build();
+        }
+
+        let shortcodes: Vec<_> = self
+            .shortcode_definitions
+            .iter()
+            .filter(|(_, def)| self.template_dependencies.depends_on(&def.tera_name, &changed))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let anchor_link_changed = self.uses_templates("anchor-link.html", &changed);
+        let admonition_changed = self.config.markdown.render_admonitions
+            && self.uses_templates("admonition.html", &changed);
+        if anchor_link_changed || admonition_changed || !shortcodes.is_empty() {
+            self.render_markdown_if(|raw_content| {
+                anchor_link_changed
+                    || (admonition_changed && raw_content.contains("[!"))
+                    || shortcodes.iter().any(|s| raw_content.contains(s.as_str()))
+            })?;
+            // The content of those pages can show up anywhere so we need to render everything
+            return self.build();
+        }
+
+        self.render_dependents(&changed)
+    }
+
+    /// Whether rendering the template `name`, or its fallback from the theme or the built-in ones,
+    /// uses any of the `changed` templates
+    fn uses_templates(&self, name: &str, changed: &HashSet<String>) -> bool {
+        match check_template_fallbacks(name, &self.tera, &self.config.theme) {
+            Some(template) => self.template_dependencies.depends_on(template, changed),
+            None => false,
+        }
+    }
+
+    /// Whether rendering the pages of the given taxonomy uses any of the `changed` templates
+    fn taxonomy_uses_templates(&self, taxonomy: &Taxonomy, changed: &HashSet<String>) -> bool {
+        ["list.html", "single.html"].iter().any(|page| {
+            let specific = format!("{}/{}", taxonomy.kind.name, page);
+            if check_template_fallbacks(&specific, &self.tera, &self.config.theme).is_some() {
+                self.uses_templates(&specific, changed)
+            } else {
+                self.uses_templates(&format!("taxonomy_{}", page), changed)
+            }
+        })
+    }
+
+    /// Renders only what uses any of the `changed` templates
+    fn render_dependents(&self, changed: &HashSet<String>) -> Result<()> {
+        // Those are rendered in many places, not worth trying to be smart
+        if self.uses_templates(&self.config.feed_filename, changed)
+            || self.uses_templates("internal/alias.html", changed)
+        {
+            return self.build();
+        }
+
+        let library = self.library.read().unwrap();
+        library
+            .pages_values()
+            .into_par_iter()
+            .filter(|p| {
+                self.uses_templates(p.meta.template.as_deref().unwrap_or("page.html"), changed)
+            })
+            .map(|p| self.render_page(p))
+            .collect::<Result<()>>()?;
+        library
+            .sections_values()
+            .into_par_iter()
+            .filter(|s| self.uses_templates(s.get_template_name(), changed))
+            .map(|s| self.render_section(s, false))
+            .collect::<Result<()>>()?;
+
+        for taxonomy in &self.taxonomies {
+            if self.taxonomy_uses_templates(taxonomy, changed) {
+                self.render_taxonomy(taxonomy)?;
+            }
+        }
+        if self.uses_templates("404.html", changed) {
+            self.render_404()?;
+        }
+        if self.uses_templates("robots.txt", changed) {
+            self.render_robots()?;
+        }
+        if self.uses_templates("sitemap.xml", changed)
+            || self.uses_templates("split_sitemap_index.xml", changed)
+        {
+            self.render_sitemap()?;
+        }
+
+        Ok(())
     }
 
     pub fn set_base_url(&mut self, base_url: String) {
@@ -158,12 +385,35 @@ impl Site {
     }
 
     pub fn set_output_path<P: AsRef<Path>>(&mut self, path: P) {
-        self.output_path = path.as_ref().to_path_buf();
+        match self.sync {
+            Some((ref mut output_path, _)) => *output_path = path.as_ref().to_path_buf(),
+            None => self.output_path = path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Build the site in the given cache directory and then only update the files of the output
+    /// directory that changed, instead of emptying it. The files the previous build wrote that are
+    /// not generated anymore are deleted.
+    /// Only used with `zola build`, has no effect in serve mode.
+    /// Needs to be called before loading it
+    pub fn enable_output_sync<P: AsRef<Path>>(&mut self, cache_path: P) {
+        let build_path = cache_path.as_ref().join(sync::BUILD_DIR);
+        let output_path = std::mem::replace(&mut self.output_path, build_path);
+        self.sync = Some((output_path, cache_path.as_ref().join(sync::MANIFEST_FILENAME)));
+    }
+
+    /// Where the site ends up, which is not where it is built when syncing
+    pub fn final_output_path(&self) -> &Path {
+        match self.sync {
+            Some((ref output_path, _)) => output_path,
+            None => &self.output_path,
+        }
     }
 
     /// Reads all .md files in the `content` directory and create pages/sections
     /// out of them
     pub fn load(&mut self) -> Result<()> {
+        let start = Instant::now();
         let base_path = self.base_path.to_string_lossy().replace("\\", "/");
 
         self.library = Arc::new(RwLock::new(Library::new(0, 0, self.config.is_multilingual())));
@@ -172,7 +422,10 @@ impl Site {
         // not the most elegant loop, but this is necessary to use skip_current_dir
         // which we can only decide to use after we've deserialised the section
         // so it's kinda necessecary
-        let mut dir_walker = WalkDir::new(format!("{}/{}", base_path, "content/")).into_iter();
+        // Sorted so the pages are always inserted in the same order
+        let mut dir_walker = WalkDir::new(format!("{}/{}", base_path, "content/"))
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter();
         let mut allowed_index_filenames: Vec<_> = self
             .config
             .other_languages()
@@ -216,6 +469,21 @@ impl Site {
                 continue;
             }
 
+            // In a partial build, we only load what's in the paths we were given and
+            // the sections above them. We still need the permalinks of everything else
+            // to resolve internal links.
+            if self.is_partial_build() {
+                let in_subset = self.only_paths.iter().any(|p| path.starts_with(p));
+                let is_ancestor =
+                    path.is_dir() && self.only_paths.iter().any(|p| p.starts_with(path));
+                if !in_subset && !is_ancestor {
+                    if self.add_permalinks_only(path, &allowed_index_filenames)? {
+                        dir_walker.skip_current_dir();
+                    }
+                    continue;
+                }
+            }
+
             // is it a section or not?
             if path.is_dir() {
                 // if we are processing a section we have to collect
@@ -258,8 +526,8 @@ impl Site {
             } else {
                 let page = Page::from_file(path, &self.config, &self.base_path)?;
 
-                // should we skip drafts?
-                if page.meta.draft && !self.include_drafts {
+                // should we skip drafts and unpublished pages?
+                if !self.should_load_page(&page) {
                     continue;
                 }
                 pages_insert_anchors.insert(
@@ -294,6 +562,11 @@ impl Site {
             link_checking::check_external_links(self)?;
         }
 
+        if let Some(ref report) = self.report {
+            report.reset();
+        }
+        self.log_time(start, "Loaded content");
+
         Ok(())
     }
 
@@ -340,8 +613,13 @@ impl Site {
     }
 
     /// Render the markdown of all pages/sections
-    /// Used in a build and in `serve` if a shortcode has changed
     pub fn render_markdown(&mut self) -> Result<()> {
+        self.render_markdown_if(|_| true)
+    }
+
+    /// Render the markdown of the pages/sections whose raw content matches the given predicate
+    /// Used in `serve` if a shortcode has changed
+    fn render_markdown_if(&mut self, predicate: impl Fn(&str) -> bool + Sync) -> Result<()> {
         // Another silly thing needed to not borrow &self in parallel and
         // make the borrow checker happy
         let permalinks = &self.permalinks;
@@ -361,6 +639,7 @@ impl Site {
         library
             .pages_mut()
             .values_mut()
+            .filter(|page| predicate(&page.raw_content))
             .collect::<Vec<_>>()
             .par_iter_mut()
             .map(|page| {
@@ -371,6 +650,7 @@ impl Site {
                     config,
                     insert_anchor,
                     &self.shortcode_definitions,
+                    Some(&*self.imageproc),
                 )
             })
             .collect::<Result<()>>()?;
@@ -378,16 +658,49 @@ impl Site {
         library
             .sections_mut()
             .values_mut()
+            .filter(|section| predicate(&section.raw_content))
             .collect::<Vec<_>>()
             .par_iter_mut()
             .map(|section| {
-                section.render_markdown(permalinks, tera, config, &self.shortcode_definitions)
+                section.render_markdown(
+                    permalinks,
+                    tera,
+                    config,
+                    &self.shortcode_definitions,
+                    Some(&*self.imageproc),
+                )
             })
             .collect::<Result<()>>()?;
 
         Ok(())
     }
 
+    /// Only records the permalink of the page at `path`, or of the section if it is a directory,
+    /// for content outside of a partial build.
+    /// Returns whether the content of the directory should be skipped as the section is a draft.
+    fn add_permalinks_only(&mut self, path: &Path, index_filenames: &[String]) -> Result<bool> {
+        if !path.is_dir() {
+            let page = Page::from_file(path, &self.config, &self.base_path)?;
+            if self.should_load_page(&page) {
+                self.permalinks.insert(page.file.relative, page.permalink);
+            }
+            return Ok(false);
+        }
+
+        for filename in index_filenames {
+            let index_path = path.join(filename);
+            if !index_path.exists() {
+                continue;
+            }
+            let section = Section::from_file(&index_path, &self.config, &self.base_path)?;
+            if section.meta.draft && !self.include_drafts {
+                return Ok(true);
+            }
+            self.permalinks.insert(section.file.relative, section.permalink);
+        }
+        Ok(false)
+    }
+
     /// Add a page to the site
     /// The `render` parameter is used in the serve command with --fast, when rebuilding a page.
     pub fn add_page(&mut self, mut page: Page, render_md: bool) -> Result<()> {
@@ -401,6 +714,7 @@ impl Site {
                 &self.config,
                 insert_anchor,
                 &self.shortcode_definitions,
+                Some(&*self.imageproc),
             )?;
         }
 
@@ -433,6 +747,7 @@ impl Site {
                 &self.tera,
                 &self.config,
                 &self.shortcode_definitions,
+                Some(&*self.imageproc),
             )?;
         }
         let mut library = self.library.write().expect("Get lock for add_section");
@@ -518,7 +833,80 @@ impl Site {
         if self.static_path.exists() {
             copy_directory(&self.static_path, &self.output_path, self.config.hard_link_static)?;
         }
+        // Compiled Sass files were already moved so only the static files are left
+        for (path, fingerprinted) in self.assets.read().unwrap().iter() {
+            if self.output_path.join(path).is_file() {
+                assets::move_output_file(&self.output_path, path, fingerprinted)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The `static` folders, the theme one first as the user files overwrite it
+    fn static_directories(&self) -> Vec<PathBuf> {
+        let mut directories = Vec::new();
+        if let Some(ref theme) = self.config.theme {
+            directories.push(self.base_path.join("themes").join(theme).join("static"));
+        }
+        directories.push(self.static_path.clone());
+        directories
+    }
+
+    /// The globs of the assets to fingerprint, if any. Only used with `zola build`.
+    fn fingerprint_globs(&self) -> Option<&GlobSet> {
+        self.config
+            .fingerprint_assets_globset
+            .as_ref()
+            .filter(|_| self.build_mode == BuildMode::Disk)
+    }
+
+    /// Moves the compiled Sass files matching `fingerprint_assets` to their fingerprinted path
+    fn fingerprint_compiled_files(&self, compiled: &[PathBuf]) -> Result<()> {
+        if let Some(globs) = self.fingerprint_globs() {
+            let assets = assets::fingerprint_output_files(&self.output_path, compiled, globs)?;
+            self.assets.write().unwrap().extend(assets);
+        }
+        Ok(())
+    }
+
+    /// Writes the original and fingerprinted paths of all the fingerprinted assets
+    pub fn render_asset_manifest(&self) -> Result<()> {
+        // Sorted so the file doesn't change between builds
+        let assets: BTreeMap<_, _> = self.assets.read().unwrap().clone().into_iter().collect();
+        let content = serde_json::to_string_pretty(&assets)
+            .map_err(|e| Error::chain("Failed to serialize the asset manifest", e))?;
+        self.write_content(&[], assets::ASSET_MANIFEST_FILENAME, content, false)?;
+        Ok(())
+    }
 
+    /// The response headers and redirects of the config and of the `_headers` and `_redirects`
+    /// files of the static directories
+    pub fn response_rules(&self) -> Result<ResponseRules> {
+        ResponseRules::load(&self.config, &self.static_directories())
+    }
+
+    /// Writes the headers and redirects of the config to the `_headers` and `_redirects` files,
+    /// after the rules of the static ones
+    pub fn render_headers_and_redirects(&self) -> Result<()> {
+        // Making sure they are all valid
+        self.response_rules()?;
+        let files = [
+            (HEADERS_FILENAME, headers::headers_file(&self.config.headers)),
+            (REDIRECTS_FILENAME, headers::redirects_file(&self.config.redirects)),
+        ];
+        for (filename, rules) in &files {
+            if rules.is_empty() {
+                continue;
+            }
+            let mut content =
+                headers::read_static_file(&self.static_directories(), filename).unwrap_or_default();
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(rules);
+            self.write_content(&[], filename, content, false)?;
+        }
         Ok(())
     }
 
@@ -572,6 +960,12 @@ impl Site {
             create_directory(&current_path)?;
         }
 
+        let content = if filename.ends_with("html") && !self.config.post_process.is_empty() {
+            post_process::post_process(content, &site_path.join(filename), &self.config)?
+        } else {
+            content
+        };
+
         let final_content = if !filename.ends_with("html") || !self.config.minify_html {
             content
         } else {
@@ -585,11 +979,14 @@ impl Site {
             BuildMode::Disk => {
                 let end_path = current_path.join(filename);
                 create_file(&end_path, &final_content)?;
+                self.record_output(&site_path.join(filename));
             }
             BuildMode::Memory => {
                 let site_path =
                     if filename != "index.html" { site_path.join(filename) } else { site_path };
 
+                // It might have been a redirect before
+                SITE_REDIRECTS.write().unwrap().remove(&site_path);
                 SITE_CONTENT.write().unwrap().insert(site_path, final_content);
             }
         }
@@ -598,16 +995,64 @@ impl Site {
     }
 
     fn copy_asset(&self, src: &Path, dest: &Path) -> Result<()> {
-        copy_file_if_needed(src, dest, self.config.hard_link_static)
+        copy_file_if_needed(src, dest, self.config.hard_link_static)?;
+        if let Ok(relative) = dest.strip_prefix(&self.output_path) {
+            self.record_output(&RelativePathBuf::from_path(relative).unwrap());
+        }
+        Ok(())
+    }
+
+    /// Keeps track of the files written by the build, if we have a build cache
+    fn record_output(&self, path: &RelativePath) {
+        if let Some(ref cache) = *self.build_cache.read().unwrap() {
+            cache.record_output(path.as_str());
+        }
+    }
+
+    /// Whether the output at `components`/index.html, rendered with `template`, is already up to
+    /// date according to the build cache. `hash_fn` is only called if there is a cache.
+    fn is_output_fresh(
+        &self,
+        components: &[&str],
+        template: &str,
+        hash_fn: impl Fn(&BuildCache) -> u64,
+    ) -> bool {
+        match *self.build_cache.read().unwrap() {
+            Some(ref cache) => {
+                let template = check_template_fallbacks(template, &self.tera, &self.config.theme);
+                // We can't know whether the output of those changed
+                let calls_volatile_fns = template
+                    .map_or(false, |t| self.template_dependencies.calls_any(t, VOLATILE_FNS));
+                if calls_volatile_fns {
+                    return false;
+                }
+                let mut output = RelativePathBuf::new();
+                for component in components {
+                    output.push(component);
+                }
+                cache.is_fresh(output.join("index.html").as_str(), hash_fn(cache))
+            }
+            None => false,
+        }
     }
 
     /// Renders a single content page
     pub fn render_page(&self, page: &Page) -> Result<()> {
-        let output = page.render_html(&self.tera, &self.config, &self.library.read().unwrap())?;
-        let content = self.inject_livereload(output);
+        let library = self.library.read().unwrap();
         let components: Vec<&str> = page.path.split('/').collect();
+        let template = page.meta.template.as_deref().unwrap_or("page.html");
         let current_path =
-            self.write_content(&components, "index.html", content, !page.assets.is_empty())?;
+            if self.is_output_fresh(&components, template, |c| c.hash_page(page, &library)) {
+                components.iter().fold(self.output_path.clone(), |p, c| p.join(c))
+            } else {
+                let start = Instant::now();
+                let output = page.render_html(&self.tera, &self.config, &library)?;
+                if let Some(ref report) = self.report {
+                    report.add_page_timing(&page.file.relative, start.elapsed());
+                }
+                let content = self.inject_livereload(output);
+                self.write_content(&components, "index.html", content, !page.assets.is_empty())?
+            };
 
         // Copy any asset we found previously into the same directory as the index.html
         for asset in &page.assets {
@@ -625,43 +1070,91 @@ impl Site {
         Ok(())
     }
 
+    /// Loads the build cache if incremental builds are enabled
+    fn load_build_cache(&self) -> Result<()> {
+        let cache = match self.cache_path {
+            // The cache would delete the outputs of the content not loaded in a partial build
+            Some((ref path, ref zola_version))
+                if self.build_mode == BuildMode::Disk && !self.is_partial_build() =>
+            {
+                let fingerprint = Fingerprint::compute(
+                    &self.base_path,
+                    &self.config_file,
+                    &self.config,
+                    zola_version,
+                )?;
+                let mut cache = BuildCache::load(path, &self.output_path, fingerprint);
+                cache.set_library(
+                    &self.library.read().unwrap(),
+                    &self.permalinks,
+                    self.template_dependencies.any_calls_any(LIBRARY_FNS),
+                );
+                Some(cache)
+            }
+            _ => None,
+        };
+        *self.build_cache.write().unwrap() = cache;
+        Ok(())
+    }
+
     /// Deletes the `public` directory (only for `zola build`) and builds the site
+    /// If the build cache is enabled and still valid, the `public` directory is kept and only
+    /// the outputs that changed are rendered again.
     pub fn build(&self) -> Result<()> {
         let mut start = Instant::now();
+        if let Some(ref report) = self.report {
+            self.report_content(report);
+        }
+        self.load_build_cache()?;
+        let keep_output = self.build_cache.read().unwrap().as_ref().map_or(false, |c| c.is_valid());
         // Do not clean on `zola serve` otherwise we end up copying assets all the time
-        if self.build_mode == BuildMode::Disk {
+        if self.build_mode == BuildMode::Disk && !keep_output {
             self.clean()?;
         }
-        start = log_time(start, "Cleaned folder");
+        // The redirects of removed aliases would otherwise be kept forever
+        if self.build_mode == BuildMode::Memory {
+            SITE_REDIRECTS.write().unwrap().clear();
+        }
+        start = self.log_time(start, "Cleaned folder");
 
         // Generate/move all assets before rendering any content
+        self.assets.write().unwrap().clear();
         if let Some(ref theme) = self.config.theme {
             let theme_path = self.base_path.join("themes").join(theme);
             if theme_path.join("sass").exists() {
-                sass::compile_sass(&theme_path, &self.output_path)?;
-                start = log_time(start, "Compiled theme Sass");
+                let compiled = sass::compile_sass(&theme_path, &self.output_path)?;
+                self.fingerprint_compiled_files(&compiled)?;
+                start = self.log_time(start, "Compiled theme Sass");
             }
         }
 
         if self.config.compile_sass {
-            sass::compile_sass(&self.base_path, &self.output_path)?;
-            start = log_time(start, "Compiled own Sass");
+            let compiled = sass::compile_sass(&self.base_path, &self.output_path)?;
+            self.fingerprint_compiled_files(&compiled)?;
+            start = self.log_time(start, "Compiled own Sass");
+        }
+
+        // The static files are only copied at the end but we need their names to render
+        if let Some(globs) = self.fingerprint_globs() {
+            let assets = assets::fingerprint_static_files(&self.static_directories(), globs)?;
+            self.assets.write().unwrap().extend(assets);
+            start = self.log_time(start, "Fingerprinted static files");
         }
 
         if self.config.build_search_index {
             self.build_search_index()?;
-            start = log_time(start, "Built search index");
+            start = self.log_time(start, "Built search index");
         }
 
         // Render aliases first to allow overwriting
         self.render_aliases()?;
-        start = log_time(start, "Rendered aliases");
+        start = self.log_time(start, "Rendered aliases");
         self.render_sections()?;
-        start = log_time(start, "Rendered sections");
+        start = self.log_time(start, "Rendered sections");
         self.render_orphan_pages()?;
-        start = log_time(start, "Rendered orphan pages");
+        start = self.log_time(start, "Rendered orphan pages");
         self.render_sitemap()?;
-        start = log_time(start, "Rendered sitemap");
+        start = self.log_time(start, "Rendered sitemap");
 
         let library = self.library.read().unwrap();
         if self.config.generate_feed {
@@ -677,7 +1170,7 @@ impl Site {
                 library.pages_values()
             };
             self.render_feed(pages, None, &self.config.default_language, |c| c)?;
-            start = log_time(start, "Generated feed in default language");
+            start = self.log_time(start, "Generated feed in default language");
         }
 
         for (code, language) in &self.config.other_languages() {
@@ -687,27 +1180,94 @@ impl Site {
             let pages =
                 library.pages_values().iter().filter(|p| &p.lang == code).cloned().collect();
             self.render_feed(pages, Some(&PathBuf::from(code)), code, |c| c)?;
-            start = log_time(start, "Generated feed in other language");
+            start = self.log_time(start, "Generated feed in other language");
         }
         self.render_themes_css()?;
-        start = log_time(start, "Rendered themes css");
+        start = self.log_time(start, "Rendered themes css");
         self.render_404()?;
-        start = log_time(start, "Rendered 404");
+        start = self.log_time(start, "Rendered 404");
         self.render_robots()?;
-        start = log_time(start, "Rendered robots.txt");
+        start = self.log_time(start, "Rendered robots.txt");
         self.render_taxonomies()?;
-        start = log_time(start, "Rendered taxonomies");
+        start = self.log_time(start, "Rendered taxonomies");
         // We process images at the end as we might have picked up images to process from markdown
         // or from templates
+        if let Some(ref report) = self.report {
+            report.set_image_count(self.num_img_ops());
+        }
         self.process_images()?;
-        start = log_time(start, "Processed images");
+        start = self.log_time(start, "Processed images");
         // Processed images will be in static so the last step is to copy it
         self.copy_static_directories()?;
-        log_time(start, "Copied static dir");
+        start = self.log_time(start, "Copied static dir");
+        if self.config.generate_headers_and_redirects && self.build_mode == BuildMode::Disk {
+            self.render_headers_and_redirects()?;
+            start = self.log_time(start, "Rendered headers and redirects");
+        }
+        if self.fingerprint_globs().is_some() {
+            self.render_asset_manifest()?;
+            start = self.log_time(start, "Rendered asset manifest");
+        }
+        // Last so every file gets compressed
+        if let Some(compressor) = Compressor::new(&self.config.compress_output) {
+            if self.build_mode == BuildMode::Disk {
+                for path in compressor.compress_directory(&self.output_path)? {
+                    self.record_output(&path);
+                }
+                start = self.log_time(start, "Compressed output");
+            }
+        }
+
+        if let Some(cache) = self.build_cache.write().unwrap().take() {
+            cache.remove_stale_outputs()?;
+            cache.save()?;
+            start = self.log_time(start, "Saved build cache");
+        }
+
+        if let Some((ref output_path, ref manifest_path)) = self.sync {
+            if self.build_mode == BuildMode::Disk {
+                sync::sync_output(&self.output_path, output_path, manifest_path)?;
+                self.log_time(start, "Synced output directory");
+            }
+        }
 
         Ok(())
     }
 
+    /// Prints how long a step of the build took if `ZOLA_PERF_LOG` is set and adds it to the
+    /// build report if there is one
+    fn log_time(&self, start: Instant, message: &str) -> Instant {
+        let do_print = std::env::var("ZOLA_PERF_LOG").is_ok();
+        let now = Instant::now();
+        if do_print {
+            println!("{} took {}ms", message, now.duration_since(start).as_millis());
+        }
+        if let Some(ref report) = self.report {
+            report.add_phase(message, now.duration_since(start));
+        }
+        now
+    }
+
+    /// Adds the size of the site and the pages that were ignored to the build report
+    fn report_content(&self, report: &BuildReport) {
+        let library = self.library.read().unwrap();
+        report.set_counts(Counts {
+            pages: library.pages().len(),
+            // The index is not counted as a section, like in the console output
+            sections: library.sections().len() - 1,
+            taxonomy_terms: self.taxonomies.iter().map(|t| t.items.len()).sum(),
+            images: 0,
+        });
+        for section in library.sections_values() {
+            for key in &section.ignored_pages {
+                report.add_warning(format!(
+                    "Page ignored (missing date or weight in a sorted section): {}",
+                    library.get_page_by_key(*key).file.path.display()
+                ));
+            }
+        }
+    }
+
     pub fn render_themes_css(&self) -> Result<()> {
         ensure_directory_exists(&self.static_path)?;
 
@@ -770,8 +1330,27 @@ impl Site {
             }
             None => "index.html",
         };
+        self.render_redirect(&split, page_name, permalink, false)
+    }
+
+    /// Renders the magic HTML template redirecting to `permalink`. In serve mode, the path is
+    /// also recorded so the server can answer with a real redirect.
+    fn render_redirect(
+        &self,
+        components: &[&str],
+        filename: &str,
+        permalink: &str,
+        create_dirs: bool,
+    ) -> Result<()> {
         let content = render_redirect_template(permalink, &self.tera)?;
-        self.write_content(&split, page_name, content, false)?;
+        self.write_content(components, filename, content, create_dirs)?;
+        if self.build_mode == BuildMode::Memory {
+            let mut site_path: RelativePathBuf = components.iter().collect();
+            if filename != "index.html" {
+                site_path.push(filename);
+            }
+            SITE_REDIRECTS.write().unwrap().insert(site_path, permalink.to_string());
+        }
         Ok(())
     }
 
@@ -1038,12 +1617,7 @@ impl Site {
 
         if let Some(ref redirect_to) = section.meta.redirect_to {
             let permalink = self.config.make_permalink(redirect_to);
-            self.write_content(
-                &components,
-                "index.html",
-                render_redirect_template(&permalink, &self.tera)?,
-                create_directories,
-            )?;
+            self.render_redirect(&components, "index.html", &permalink, create_directories)?;
 
             return Ok(());
         }
@@ -1054,8 +1628,12 @@ impl Site {
                 &Paginator::from_section(section, &self.library.read().unwrap()),
             )?;
         } else {
-            let output =
-                section.render_html(&self.tera, &self.config, &self.library.read().unwrap())?;
+            let library = self.library.read().unwrap();
+            let template = section.get_template_name();
+            if self.is_output_fresh(&components, template, |c| c.hash_section(section, &library)) {
+                return Ok(());
+            }
+            let output = section.render_html(&self.tera, &self.config, &library)?;
             let content = self.inject_livereload(output);
             self.write_content(&components, "index.html", content, false)?;
         }
@@ -1115,10 +1693,10 @@ impl Site {
                     self.write_content(&pager_components, "index.html", content, false)?;
                 } else {
                     self.write_content(&index_components, "index.html", content, false)?;
-                    self.write_content(
+                    self.render_redirect(
                         &pager_components,
                         "index.html",
-                        render_redirect_template(&paginator.permalink, &self.tera)?,
+                        &paginator.permalink,
                         false,
                     )?;
                 }
@@ -1128,12 +1706,3 @@ impl Site {
             .collect::<Result<()>>()
     }
 }
-
-fn log_time(start: Instant, message: &str) -> Instant {
-    let do_print = std::env::var("ZOLA_PERF_LOG").is_ok();
-    let now = Instant::now();
-    if do_print {
-        println!("{} took {}ms", message, now.duration_since(start).as_millis());
-    }
-    now
-}
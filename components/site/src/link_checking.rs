@@ -54,6 +54,13 @@ pub fn check_internal_links_with_anchors(site: &Site) -> Result<()> {
         for part in md_path.split('/') {
             full_path.push(part);
         }
+        // Content outside of a partial build is not loaded so we can't check its anchors
+        if site.is_partial_build()
+            && !library.contains_section(&full_path)
+            && library.get_page(&full_path).is_none()
+        {
+            return false;
+        }
         if md_path.contains("_index.md") {
             let section = library
                 .get_section(&full_path)
@@ -0,0 +1,250 @@
+//! The transforms applied to every rendered HTML file before it is written,
+//! as configured in the `post_process` section of the config.
+use std::borrow::Cow;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use relative_path::RelativePath;
+use tera::escape_html;
+
+use config::{Config, PostProcess, PreloadLink};
+use errors::{bail, Error, Result};
+
+lazy_static! {
+    // Attribute values can contain `>` so we need to skip over quoted strings
+    static ref IMG_TAG_RE: Regex = Regex::new(r#"(?i)<img\b(?:[^>"']|"[^"]*"|'[^']*')*>"#).unwrap();
+    static ref TAG_RE: Regex = Regex::new(r#"<[a-zA-Z](?:[^>"']|"[^"]*"|'[^']*')*>"#).unwrap();
+    static ref CANONICAL_LINK_RE: Regex =
+        Regex::new(r#"(?i)^<link\b.*\srel\s*=\s*["']?canonical\b"#).unwrap();
+    static ref LOADING_ATTR_RE: Regex = Regex::new(r"(?i)\sloading\s*=").unwrap();
+    static ref LINK_ATTR_RE: Regex =
+        Regex::new(r#"(?i)(\s(?:href|src)\s*=\s*)(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref HEAD_END_RE: Regex = Regex::new(r"(?i)</head\s*>").unwrap();
+}
+
+/// Applies all the configured transforms to the HTML file found at `path` in the output directory
+pub fn post_process(content: String, path: &RelativePath, config: &Config) -> Result<String> {
+    config.post_process.iter().try_fold(content, |content, transform| match transform {
+        PostProcess::LazyImages => Ok(lazy_images(&content).into_owned()),
+        PostProcess::RelativeLinks => {
+            Ok(relative_links(&content, path, &config.base_url).into_owned())
+        }
+        PostProcess::Preload { links } => Ok(preload(&content, links).into_owned()),
+        PostProcess::Command { command } => run_command(content, path, command),
+    })
+}
+
+fn lazy_images(content: &str) -> Cow<'_, str> {
+    IMG_TAG_RE.replace_all(content, |caps: &Captures| {
+        let tag = &caps[0];
+        if LOADING_ATTR_RE.is_match(tag) {
+            return tag.to_string();
+        }
+        let end = if tag.ends_with("/>") { tag.len() - 2 } else { tag.len() - 1 };
+        format!("{} loading=\"lazy\"{}", tag[..end].trim_end(), &tag[end..])
+    })
+}
+
+fn relative_links<'a>(content: &'a str, path: &RelativePath, base_url: &str) -> Cow<'a, str> {
+    let base_url = base_url.trim_end_matches('/');
+    // The directory the document is in, the links are relative to it
+    let current: Vec<_> = path.parent().map(|p| p.components().collect()).unwrap_or_default();
+
+    TAG_RE.replace_all(content, |caps: &Captures| {
+        let tag = &caps[0];
+        // Canonical URLs have to be absolute
+        if CANONICAL_LINK_RE.is_match(tag) {
+            return tag.to_string();
+        }
+        LINK_ATTR_RE
+            .replace_all(tag, |caps: &Captures| {
+                let (value, quote) = match caps.get(2) {
+                    Some(v) => (v.as_str(), '"'),
+                    None => (&caps[3], '\''),
+                };
+                let rest = match value.strip_prefix(base_url) {
+                    Some(rest) if rest.is_empty() || rest.starts_with(&['/', '?', '#'][..]) => rest,
+                    _ => return caps[0].to_string(),
+                };
+                format!("{}{}{}{}", &caps[1], quote, make_relative(&current, rest), quote)
+            })
+            .into_owned()
+    })
+}
+
+/// Makes `target`, a path from the root of the site optionally followed by a query string
+/// and/or fragment, relative to the `current` directory
+fn make_relative(current: &[relative_path::Component], target: &str) -> String {
+    let split = target.find(&['?', '#'][..]).unwrap_or(target.len());
+    let (target_path, suffix) = target.split_at(split);
+    let target_path = RelativePath::new(target_path.trim_start_matches('/'));
+    let target: Vec<_> = target_path.components().collect();
+
+    let common = current.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut relative = "../".repeat(current.len() - common);
+    relative.push_str(&target[common..].iter().map(|c| c.as_str()).collect::<Vec<_>>().join("/"));
+    if !target[common..].is_empty() && target_path.as_str().ends_with('/') {
+        relative.push('/');
+    }
+    if relative.is_empty() {
+        relative.push_str("./");
+    }
+    relative.push_str(suffix);
+    relative
+}
+
+fn preload<'a>(content: &'a str, links: &[PreloadLink]) -> Cow<'a, str> {
+    let mut tags = String::new();
+    for link in links {
+        tags.push_str(&format!(
+            r#"<link rel="preload" href="{}" as="{}""#,
+            escape_html(&link.href),
+            escape_html(&link.r#as)
+        ));
+        if let Some(ref mime) = link.r#type {
+            tags.push_str(&format!(r#" type="{}""#, escape_html(mime)));
+        }
+        if link.crossorigin {
+            tags.push_str(" crossorigin");
+        }
+        tags.push('>');
+    }
+    HEAD_END_RE.replace(content, |caps: &Captures| format!("{}{}", tags, &caps[0]))
+}
+
+fn run_command(content: String, path: &RelativePath, command: &[String]) -> Result<String> {
+    let mut child = Command::new(&command[0])
+        .args(&command[1..])
+        .env("ZOLA_OUTPUT_FILE", path.as_str())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            Error::chain(format!("Failed to run post-processing command `{}`", command[0]), e)
+        })?;
+
+    // Write from another thread, the command could fill its stdout before reading all of stdin
+    let mut stdin = child.stdin.take().unwrap();
+    let writer = std::thread::spawn(move || stdin.write_all(content.as_bytes()));
+    let output = child.wait_with_output().map_err(|e| {
+        Error::chain(format!("Failed to run post-processing command `{}`", command[0]), e)
+    })?;
+    // The command is allowed to not read all of its input
+    let _ = writer.join();
+
+    if !output.status.success() {
+        bail!(
+            "Post-processing command `{}` failed on {} ({}): {}",
+            command.join(" "),
+            path,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).map_err(|e| {
+        Error::chain(format!("Post-processing command `{}` did not output UTF-8", command[0]), e)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_add_lazy_loading_to_images() {
+        let html =
+            r#"<img src="a.png"><IMG alt="a > b" src="b.png" /><img src="c.png" loading="eager">"#;
+        assert_eq!(
+            lazy_images(html),
+            r#"<img src="a.png" loading="lazy"><IMG alt="a > b" src="b.png" loading="lazy"/><img src="c.png" loading="eager">"#
+        );
+    }
+
+    #[test]
+    fn can_make_links_relative() {
+        let html = r#"<a href="https://example.com/blog/other/#top">a</a><img src='https://example.com/img.png'><a href="https://example.com">home</a><a href="https://example.community/">b</a>"#;
+        let path = RelativePath::new("blog/post/index.html");
+        assert_eq!(
+            relative_links(html, path, "https://example.com/"),
+            r#"<a href="../other/#top">a</a><img src='../../img.png'><a href="../../">home</a><a href="https://example.community/">b</a>"#
+        );
+    }
+
+    #[test]
+    fn can_make_links_relative_from_root() {
+        let path = RelativePath::new("index.html");
+        assert_eq!(
+            relative_links(r#"<a href="https://example.com/">a</a>"#, path, "https://example.com"),
+            r#"<a href="./">a</a>"#
+        );
+        assert_eq!(
+            relative_links(
+                r#"<a href="https://example.com/a/?q=1">a</a>"#,
+                path,
+                "https://example.com"
+            ),
+            r#"<a href="a/?q=1">a</a>"#
+        );
+    }
+
+    #[test]
+    fn keeps_canonical_links_absolute() {
+        let html = r#"<link rel="canonical" href="https://example.com/blog/"><LINK href='https://example.com/blog/' REL=canonical><link rel="stylesheet" href="https://example.com/style.css">"#;
+        let path = RelativePath::new("blog/index.html");
+        assert_eq!(
+            relative_links(html, path, "https://example.com/"),
+            r#"<link rel="canonical" href="https://example.com/blog/"><LINK href='https://example.com/blog/' REL=canonical><link rel="stylesheet" href="../style.css">"#
+        );
+    }
+
+    #[test]
+    fn can_preload_links() {
+        let links = vec![
+            PreloadLink {
+                href: "/fonts/a.woff2".to_string(),
+                r#as: "font".to_string(),
+                r#type: Some("font/woff2".to_string()),
+                crossorigin: true,
+            },
+            PreloadLink {
+                href: "/style.css".to_string(),
+                r#as: "style".to_string(),
+                r#type: None,
+                crossorigin: false,
+            },
+        ];
+        assert_eq!(
+            preload("<html><head><title>a</title></head><body></body></html>", &links),
+            r#"<html><head><title>a</title><link rel="preload" href="&#x2F;fonts&#x2F;a.woff2" as="font" type="font&#x2F;woff2" crossorigin><link rel="preload" href="&#x2F;style.css" as="style"></head><body></body></html>"#
+        );
+        assert_eq!(preload("<p>no head</p>", &links), "<p>no head</p>");
+    }
+
+    #[test]
+    fn can_preload_links_with_special_characters() {
+        let links = vec![PreloadLink {
+            href: r#"style.css?a=1&b="2""#.to_string(),
+            r#as: "style".to_string(),
+            r#type: Some("text/css\"><script>".to_string()),
+            crossorigin: false,
+        }];
+        assert_eq!(
+            preload("<head></head>", &links),
+            r#"<head><link rel="preload" href="style.css?a=1&amp;b=&quot;2&quot;" as="style" type="text&#x2F;css&quot;&gt;&lt;script&gt;"></head>"#
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_run_command() {
+        let path = RelativePath::new("index.html");
+        let command = vec!["tr".to_string(), "a-z".to_string(), "A-Z".to_string()];
+        assert_eq!(run_command("<p>hi</p>".to_string(), path, &command).unwrap(), "<P>HI</P>");
+
+        let command = vec!["false".to_string()];
+        assert!(run_command("<p>hi</p>".to_string(), path, &command).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! The machine-readable report written by `zola build --report`.
+//!
+//! It is filled in while the site is built and contains the time taken by each step,
+//! how much content was rendered, the files in the output directory and the slowest pages.
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use relative_path::RelativePathBuf;
+use serde_derive::Serialize;
+use walkdir::WalkDir;
+
+use errors::{Error, Result};
+use utils::fs::create_file;
+
+/// How many of the slowest pages to keep in the report
+const SLOWEST_PAGES: usize = 10;
+
+#[derive(Debug, Serialize)]
+struct Phase {
+    name: String,
+    duration_ms: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Counts {
+    pub pages: usize,
+    pub sections: usize,
+    pub taxonomy_terms: usize,
+    pub images: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputFile {
+    /// Relative to the output directory, always with `/` as separator
+    path: String,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct PageTiming {
+    /// Path of the Markdown file, relative to the `content` directory
+    path: String,
+    duration_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct ReportFile<'a> {
+    total_duration_ms: f64,
+    phases: &'a [Phase],
+    counts: &'a Counts,
+    total_output_size: u64,
+    files: Vec<OutputFile>,
+    warnings: &'a [String],
+    slowest_pages: &'a [PageTiming],
+}
+
+/// Collects the data for the report during a build
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    phases: Mutex<Vec<Phase>>,
+    counts: Mutex<Counts>,
+    warnings: Mutex<Vec<String>>,
+    page_timings: Mutex<Vec<PageTiming>>,
+}
+
+impl BuildReport {
+    /// Empties the report, done every time the content is loaded
+    pub fn reset(&self) {
+        self.phases.lock().unwrap().clear();
+        *self.counts.lock().unwrap() = Counts::default();
+        self.warnings.lock().unwrap().clear();
+        self.page_timings.lock().unwrap().clear();
+    }
+
+    pub fn add_phase(&self, name: &str, duration: Duration) {
+        self.phases
+            .lock()
+            .unwrap()
+            .push(Phase { name: name.to_string(), duration_ms: to_ms(duration) });
+    }
+
+    pub fn set_counts(&self, counts: Counts) {
+        *self.counts.lock().unwrap() = counts;
+    }
+
+    /// Images are only known once everything is rendered
+    pub fn set_image_count(&self, images: usize) {
+        self.counts.lock().unwrap().images = images;
+    }
+
+    pub fn add_warning(&self, message: String) {
+        self.warnings.lock().unwrap().push(message);
+    }
+
+    pub fn add_page_timing(&self, path: &str, duration: Duration) {
+        self.page_timings
+            .lock()
+            .unwrap()
+            .push(PageTiming { path: path.to_string(), duration_ms: to_ms(duration) });
+    }
+
+    /// Writes the report as JSON at `path`, listing the files currently in `output_path`
+    pub fn write(&self, path: &Path, output_path: &Path) -> Result<()> {
+        let phases = self.phases.lock().unwrap();
+        let counts = self.counts.lock().unwrap();
+        let warnings = self.warnings.lock().unwrap();
+        let mut page_timings = self.page_timings.lock().unwrap();
+        // The durations are never NaN
+        page_timings.sort_by(|a, b| b.duration_ms.partial_cmp(&a.duration_ms).unwrap());
+        page_timings.truncate(SLOWEST_PAGES);
+
+        let mut files = Vec::new();
+        for entry in WalkDir::new(output_path).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+            let entry =
+                entry.map_err(|e| Error::chain("Failed to list the output directory", e))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let size = entry
+                .metadata()
+                .map_err(|e| {
+                    Error::chain(
+                        format!("Failed to read metadata of {}", entry.path().display()),
+                        e,
+                    )
+                })?
+                .len();
+            let relative = entry.path().strip_prefix(output_path).unwrap();
+            let path = RelativePathBuf::from_path(relative).unwrap().to_string();
+            files.push(OutputFile { path, size });
+        }
+
+        let report = ReportFile {
+            total_duration_ms: phases.iter().map(|p| p.duration_ms).sum(),
+            phases: &phases,
+            counts: &counts,
+            total_output_size: files.iter().map(|f| f.size).sum(),
+            files,
+            warnings: &warnings,
+            slowest_pages: &page_timings,
+        };
+        let content = serde_json::to_string_pretty(&report)
+            .map_err(|e| Error::chain("Failed to serialize the build report", e))?;
+        create_file(path, &content)
+    }
+}
+
+fn to_ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
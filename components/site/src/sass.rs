@@ -7,7 +7,8 @@ use sass_rs::{compile_file, Options, OutputStyle};
 use errors::{bail, Result};
 use utils::fs::{create_file, ensure_directory_exists};
 
-pub fn compile_sass(base_path: &Path, output_path: &Path) -> Result<()> {
+/// Compiles the Sass files of `base_path` and returns the paths of the CSS files written
+pub fn compile_sass(base_path: &Path, output_path: &Path) -> Result<Vec<PathBuf>> {
     ensure_directory_exists(output_path)?;
 
     let sass_path = {
@@ -34,7 +35,7 @@ pub fn compile_sass(base_path: &Path, output_path: &Path) -> Result<()> {
         }
     }
 
-    Ok(())
+    Ok(compiled_paths.into_iter().map(|(_, css)| css).collect())
 }
 
 fn compile_sass_glob(
@@ -0,0 +1,93 @@
+//! Updating the output directory in place, used by `zola build --sync`.
+//!
+//! The site is built as usual in another directory, which is then compared to the output
+//! directory: only the files whose content changed are written so the others keep their
+//! modification time, and the files written by the previous sync but not produced anymore
+//! are deleted. The list of the files written is kept in a manifest for the next sync.
+use std::collections::HashSet;
+use std::fs::{copy, metadata, read};
+use std::path::{Path, PathBuf};
+
+use relative_path::RelativePathBuf;
+use serde_derive::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use errors::{Error, Result};
+use utils::fs::{
+    create_directory, create_file, ensure_directory_exists, read_file,
+    remove_file_and_empty_parents,
+};
+
+/// Where the site is built before being synced, relative to the cache directory
+pub const BUILD_DIR: &str = "output";
+/// The manifest of the last sync, relative to the cache directory
+pub const MANIFEST_FILENAME: &str = "output.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    output_path: PathBuf,
+    /// All the files in the output directory that come from the build, relative to it
+    files: Vec<String>,
+}
+
+/// Makes `output_path` contain the files of `build_path`, only touching the ones that differ and
+/// deleting the ones listed in the manifest at `manifest_path` that are not in `build_path`.
+/// Any other file in `output_path` is left alone.
+pub fn sync_output(build_path: &Path, output_path: &Path, manifest_path: &Path) -> Result<()> {
+    // A manifest for another output directory doesn't tell us anything about this one
+    let previous = read_file(manifest_path)
+        .ok()
+        .and_then(|c| serde_json::from_str::<Manifest>(&c).ok())
+        .filter(|m| m.output_path == output_path)
+        .unwrap_or_default();
+
+    let mut files = Vec::new();
+    ensure_directory_exists(output_path)?;
+    for entry in WalkDir::new(build_path).sort_by(|a, b| a.file_name().cmp(b.file_name())) {
+        let entry = entry.map_err(|e| Error::chain("Failed to list the built site", e))?;
+        let relative = entry.path().strip_prefix(build_path).unwrap();
+        let dest = output_path.join(relative);
+        if entry.file_type().is_dir() {
+            create_directory(&dest)?;
+            continue;
+        }
+
+        files.push(RelativePathBuf::from_path(relative).unwrap().to_string());
+        if dest.is_file() && has_same_content(entry.path(), &dest)? {
+            continue;
+        }
+        copy(entry.path(), &dest).map_err(|e| {
+            Error::chain(
+                format!(
+                    "Was not able to copy file {} to {}",
+                    entry.path().display(),
+                    dest.display()
+                ),
+                e,
+            )
+        })?;
+    }
+
+    let produced: HashSet<_> = files.iter().collect();
+    for file in previous.files.iter().filter(|f| !produced.contains(f)) {
+        let path = output_path.join(file);
+        if path.is_file() {
+            remove_file_and_empty_parents(&path, output_path)?;
+        }
+    }
+
+    let manifest = Manifest { output_path: output_path.to_path_buf(), files };
+    if let Some(parent) = manifest_path.parent() {
+        ensure_directory_exists(parent)?;
+    }
+    let content = serde_json::to_string(&manifest)
+        .map_err(|e| Error::chain("Failed to serialize the output manifest", e))?;
+    create_file(manifest_path, &content)
+}
+
+fn has_same_content(a: &Path, b: &Path) -> Result<bool> {
+    if metadata(a)?.len() != metadata(b)?.len() {
+        return Ok(false);
+    }
+    Ok(read(a)? == read(b)?)
+}
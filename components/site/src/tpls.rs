@@ -24,6 +24,7 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
             site.config.clone(),
             site.permalinks.clone(),
             site.output_path.clone(),
+            site.assets.clone(),
         ),
     );
     site.tera.register_function(
@@ -66,8 +67,12 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
             site.base_path.clone(),
             site.config.theme.clone(),
             site.output_path.clone(),
+            site.assets.clone(),
         ),
     );
+    if let Some(timestamp) = site.reproducible_timestamp() {
+        site.tera.register_function("now", global_fns::Now::new(timestamp));
+    }
 
     Ok(())
 }
@@ -76,11 +81,19 @@ pub fn register_early_global_fns(site: &mut Site) -> TeraResult<()> {
 pub fn register_tera_global_fns(site: &mut Site) {
     site.tera.register_function(
         "get_page",
-        global_fns::GetPage::new(site.base_path.clone(), site.library.clone()),
+        global_fns::GetPage::new(
+            site.base_path.clone(),
+            site.library.clone(),
+            site.is_partial_build(),
+        ),
     );
     site.tera.register_function(
         "get_section",
-        global_fns::GetSection::new(site.base_path.clone(), site.library.clone()),
+        global_fns::GetSection::new(
+            site.base_path.clone(),
+            site.library.clone(),
+            site.is_partial_build(),
+        ),
     );
     site.tera.register_function(
         "get_taxonomy",
@@ -2,12 +2,14 @@ mod common;
 
 use std::collections::HashMap;
 use std::env;
+use std::io::Read;
 use std::path::Path;
 
 use common::{build_site, build_site_with_setup};
 use config::Taxonomy;
+use relative_path::RelativePathBuf;
 use site::sitemap;
-use site::Site;
+use site::{Site, SITE_CONTENT, SITE_REDIRECTS};
 
 #[test]
 fn can_parse_site() {
@@ -766,6 +768,289 @@ fn can_get_hash_for_static_files() {
     ));
 }
 
+#[test]
+fn reloading_a_template_only_renders_what_uses_it() {
+    let (mut site, _tmp_dir, public) = build_site("test_site");
+    let tampered = ["applying_page_template/override/index.html", "posts/python/index.html"];
+    for output in &tampered {
+        std::fs::write(public.join(output), "tampered").unwrap();
+    }
+
+    let template = site.base_path.join("templates").join("page_template_override.html");
+    site.reload_templates(&template).unwrap();
+
+    assert!(file_contains!(public, tampered[0], "Yet another page template"));
+    assert!(file_contains!(public, tampered[1], "tampered"));
+}
+
+#[test]
+fn can_write_build_report() {
+    let (site, tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.enable_build_report();
+        (site, true)
+    });
+    let report_path = tmp_dir.path().join("report.json");
+    site.build_report().unwrap().write(&report_path, &public).unwrap();
+
+    let report: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+    assert_eq!(report["phases"][0]["name"], "Loaded content");
+    assert_eq!(report["counts"]["pages"], site.library.read().unwrap().pages().len());
+    assert_eq!(report["counts"]["taxonomy_terms"], 2);
+    let files = report["files"].as_array().unwrap();
+    let sitemap = files.iter().find(|f| f["path"] == "sitemap.xml").unwrap();
+    assert_eq!(sitemap["size"], std::fs::metadata(public.join("sitemap.xml")).unwrap().len());
+    assert!(files.iter().any(|f| f["path"] == "posts/python/index.html"));
+    assert_eq!(report["slowest_pages"].as_array().unwrap().len(), 10);
+}
+
+#[test]
+fn can_build_only_part_of_the_content() {
+    let (site, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.set_only_paths(&["content/posts/tutorials"]).unwrap();
+        (site, true)
+    });
+    assert!(site.is_partial_build());
+
+    // The subset and the sections above it
+    assert!(file_exists!(public, "posts/tutorials/index.html"));
+    assert!(file_exists!(public, "posts/tutorials/programming/index.html"));
+    assert!(file_exists!(public, "posts/index.html"));
+    assert!(file_exists!(public, "index.html"));
+    // But nothing else
+    assert!(!file_exists!(public, "posts/python/index.html"));
+    assert!(!file_exists!(public, "root-page-2/index.html"));
+    assert!(!file_exists!(public, "reverse-paginated/index.html"));
+    // The permalinks of everything are still known for internal links
+    assert!(site.permalinks.contains_key("posts/python.md"));
+    assert!(site.permalinks.contains_key("reverse-paginated/_index.md"));
+    assert!(!site.permalinks.contains_key("posts/draft.md"));
+}
+
+#[test]
+fn errors_on_partial_build_outside_of_content() {
+    let mut path = env::current_dir().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    path.push("test_site");
+    let mut site = Site::new(&path, path.join("config.toml")).unwrap();
+    assert!(site.set_only_paths(&["templates"]).is_err());
+    assert!(site.set_only_paths(&["content/../config.toml"]).is_err());
+    assert!(site.set_only_paths(&["content/does-not-exist"]).is_err());
+    assert!(site.set_only_paths(&["content/posts/../posts"]).is_ok());
+}
+
+#[test]
+fn reproducible_builds_output_the_same_files() {
+    let setup = |mut site: Site| {
+        site.set_reproducible(1_600_000_000);
+        (site, true)
+    };
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", setup);
+    let (_, _other_tmp_dir, other_public) = build_site_with_setup("test_site", setup);
+
+    assert!(file_contains!(public, "rebuild/index.html", "2020-09-13T12:26:40+00:00"));
+    let list_files = |dir: &Path| {
+        walkdir::WalkDir::new(dir)
+            .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+            .into_iter()
+            .map(|e| e.unwrap().path().strip_prefix(dir).unwrap().to_path_buf())
+            .collect::<Vec<_>>()
+    };
+    let files = list_files(&public);
+    assert_eq!(files, list_files(&other_public));
+    for file in files.iter().filter(|f| public.join(f).is_file()) {
+        let content = std::fs::read(public.join(file)).unwrap();
+        assert!(content == std::fs::read(other_public.join(file)).unwrap(), "{:?} differs", file);
+    }
+
+    // The scheduled pages are loaded according to the timestamp, not to the current date
+    assert!(!file_exists!(public, "posts/scheduled/index.html"));
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        // 2100-01-15
+        site.set_reproducible(4_103_654_400);
+        (site, true)
+    });
+    assert!(file_exists!(public, "posts/scheduled/index.html"));
+}
+
+#[test]
+fn can_sync_output_directory() {
+    let mut path = env::current_dir().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    path.push("test_site");
+    let tmp_dir = tempfile::tempdir().unwrap();
+    let public = tmp_dir.path().join("public");
+    let build = || {
+        let mut site = Site::new(&path, path.join("config.toml")).unwrap();
+        site.enable_output_sync(tmp_dir.path().join("cache"));
+        site.set_output_path(&public);
+        site.load().unwrap();
+        site.build().unwrap();
+        site
+    };
+
+    let site = build();
+    assert_eq!(site.final_output_path(), public);
+    assert!(file_exists!(public, "index.html"));
+    let manifest_path = tmp_dir.path().join("cache").join("output.json");
+    let mut manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+
+    // A file written by a previous build, one not written by Zola and an outdated one
+    manifest["files"].as_array_mut().unwrap().push("old/index.html".into());
+    std::fs::write(&manifest_path, manifest.to_string()).unwrap();
+    std::fs::create_dir(public.join("old")).unwrap();
+    std::fs::write(public.join("old").join("index.html"), "old").unwrap();
+    std::fs::write(public.join("unknown.txt"), "unknown").unwrap();
+    std::fs::write(public.join("posts").join("python").join("index.html"), "tampered").unwrap();
+    let mtime = filetime::FileTime::from_unix_time(1_000_000_000, 0);
+    filetime::set_file_mtime(public.join("sitemap.xml"), mtime).unwrap();
+
+    build();
+    assert!(!public.join("old").exists());
+    assert!(file_contains!(public, "unknown.txt", "unknown"));
+    assert!(!file_contains!(public, "posts/python/index.html", "tampered"));
+    let metadata = std::fs::metadata(public.join("sitemap.xml")).unwrap();
+    assert_eq!(filetime::FileTime::from_last_modification_time(&metadata), mtime);
+}
+
+#[test]
+fn can_fingerprint_assets() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.fingerprint_assets = vec!["*.css".to_string(), "scripts/*".to_string()];
+        let mut globs = globset::GlobSetBuilder::new();
+        globs.add(globset::Glob::new("*.css").unwrap());
+        globs.add(globset::Glob::new("scripts/*").unwrap());
+        site.config.fingerprint_assets_globset = Some(globs.build().unwrap());
+        (site, true)
+    });
+
+    let manifest: HashMap<String, String> =
+        serde_json::from_str(&std::fs::read_to_string(public.join("asset-manifest.json")).unwrap())
+            .unwrap();
+    // Static files
+    assert_eq!(manifest["site.css"], "site.83bd983e8899946e.css");
+    assert_eq!(manifest["scripts/hello.js"], "scripts/hello.64a8e7b5ac001114.js");
+    // Compiled Sass
+    assert!(manifest["nested_sass/scss.css"].starts_with("nested_sass/scss."));
+    for (path, fingerprinted) in &manifest {
+        assert!(!file_exists!(public, path));
+        assert!(file_exists!(public, fingerprinted));
+    }
+    // Not matching any glob
+    assert!(!manifest.contains_key(".gitattributes"));
+    assert!(file_exists!(public, ".gitattributes"));
+
+    // `get_url` returns the fingerprinted path, even with `cachebust` and `get_file_hash` still works
+    assert!(file_contains!(
+        public,
+        "index.html",
+        "<link href=\"https://replace-this-with-your-url.com/site.83bd983e8899946e.css\" rel=\"stylesheet\">"
+    ));
+    assert!(file_contains!(
+        public,
+        "index.html",
+        "https://replace-this-with-your-url.com/scripts/hello.64a8e7b5ac001114.js"
+    ));
+    assert!(file_contains!(public, "index.html", "integrity=\"sha384-"));
+}
+
+#[test]
+fn can_compress_output() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.compress_output.gzip = true;
+        site.config.compress_output.brotli = true;
+        site.config.compress_output.min_size = 300;
+        site.config.build_search_index = true;
+        (site, true)
+    });
+
+    for path in &["index.html", "sitemap.xml", "search_index.en.js", "posts/python/index.html"] {
+        let original = std::fs::read(public.join(path)).unwrap();
+        let mut decompressed = Vec::new();
+        let gzipped = std::fs::read(public.join(format!("{}.gz", path))).unwrap();
+        flate2::read::GzDecoder::new(&gzipped[..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, original);
+        assert!(file_exists!(public, &format!("{}.br", path)));
+    }
+    // Too small
+    assert!(!file_exists!(public, "scripts/hello.js.gz"));
+    // Already compressed
+    assert!(file_exists!(public, "posts/with-assets/zola.png"));
+    assert!(!file_exists!(public, "posts/with-assets/zola.png.gz"));
+}
+
+#[test]
+fn can_generate_headers_and_redirects_files() {
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.config.generate_headers_and_redirects = true;
+        site.config.headers =
+            site::headers::parse_headers_file("/*\n  Content-Security-Policy: default-src 'self'")
+                .unwrap();
+        site.config.redirects =
+            site::headers::parse_redirects_file("/old/* /posts/:splat 302").unwrap();
+        (site, true)
+    });
+
+    assert!(file_contains!(
+        public,
+        "_headers",
+        "/*\n  Content-Security-Policy: default-src 'self'\n"
+    ));
+    assert!(file_contains!(public, "_redirects", "/old/* /posts/:splat 302\n"));
+}
+
+#[test]
+fn can_record_redirects_in_serve_mode() {
+    let (site, _tmp_dir, _public) = build_site_with_setup("test_site", |mut site| {
+        site.enable_serve_mode();
+        (site, true)
+    });
+    let base_url = &site.config.base_url;
+    let redirects = SITE_REDIRECTS.read().unwrap();
+    let redirect = |path: &str| redirects.get(&RelativePathBuf::from(path)).cloned();
+
+    // Page aliases
+    assert_eq!(
+        redirect("an-old-url/old-page"),
+        Some(format!("{}/posts/something-else/", base_url))
+    );
+    assert_eq!(
+        redirect("an-old-url/an-old-alias.html"),
+        Some(format!("{}/posts/something-else/", base_url))
+    );
+    // Section aliases
+    assert_eq!(redirect("another-old-url"), Some(format!("{}/posts/", base_url)));
+    // Sections with `redirect_to`
+    assert_eq!(
+        redirect("posts/tutorials/devops"),
+        Some(format!("{}/posts/tutorials/devops/docker/", base_url))
+    );
+    // The first page of a paginated section
+    assert_eq!(redirect("posts/page/1"), Some(format!("{}/posts/", base_url)));
+    // Content is not a redirect
+    assert_eq!(redirect("posts"), None);
+    assert!(SITE_CONTENT.read().unwrap().contains_key(&RelativePathBuf::from("404.html")));
+}
+
+#[test]
+fn can_build_site_as_of_a_date() {
+    let (_, _tmp_dir, public) = build_site("test_site");
+    assert!(!file_exists!(public, "posts/scheduled/index.html"));
+    assert!(!file_contains!(public, "sitemap.xml", "scheduled"));
+
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.set_as_of("2100-01-15").unwrap();
+        (site, true)
+    });
+    assert!(file_exists!(public, "posts/scheduled/index.html"));
+    assert!(file_contains!(public, "sitemap.xml", "scheduled"));
+
+    let (_, _tmp_dir, public) = build_site_with_setup("test_site", |mut site| {
+        site.set_as_of("2100-02-01T00:00:00").unwrap();
+        (site, true)
+    });
+    assert!(!file_exists!(public, "posts/scheduled/index.html"));
+}
+
 #[test]
 fn check_site() {
     let (mut site, _tmp_dir, _public) = build_site("test_site");
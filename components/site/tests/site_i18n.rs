@@ -198,3 +198,46 @@ fn correct_translations_on_all_pages() {
         assert!(ensure_translations_in_output(&site, path, &link));
     }
 }
+
+#[test]
+fn can_build_site_incrementally() {
+    let mut path = env::current_dir().unwrap().parent().unwrap().parent().unwrap().to_path_buf();
+    path.push("test_site_i18n");
+    let config_file = path.join("config.toml");
+    let tmp_dir = tempfile::tempdir().expect("create temp dir");
+    let public = tmp_dir.path().join("public");
+    let cache_path = tmp_dir.path().join("cache");
+
+    let build = |zola_version: &str, ignored: Option<&str>| {
+        let mut site = Site::new(&path, &config_file).unwrap();
+        site.enable_build_cache(&cache_path, zola_version);
+        if let Some(glob) = ignored {
+            let mut builder = globset::GlobSetBuilder::new();
+            builder.add(globset::Glob::new(glob).unwrap());
+            site.config.ignored_content_globset = Some(builder.build().unwrap());
+        }
+        site.load().unwrap();
+        site.set_output_path(&public);
+        site.build().expect("Couldn't build the site");
+    };
+
+    build("0.16.0", None);
+    assert!(file_exists!(cache_path, "build.json"));
+    assert!(file_exists!(public, "blog/something/index.html"));
+
+    // Nothing changed so the pages are not rendered again
+    let page = public.join("fr").join("blog").join("something").join("index.html");
+    std::fs::write(&page, "Not rendered again").unwrap();
+    build("0.16.0", None);
+    assert!(file_contains!(public, "fr/blog/something/index.html", "Not rendered again"));
+
+    // Removing a page changes the content seen by the templates and deletes its output
+    build("0.16.0", Some("*/blog/something.md"));
+    assert!(!file_exists!(public, "blog/something/index.html"));
+    assert!(!file_contains!(public, "fr/blog/something/index.html", "Not rendered again"));
+
+    // Another version of Zola might render the same content differently
+    std::fs::write(&page, "Not rendered again").unwrap();
+    build("0.16.1", Some("*/blog/something.md"));
+    assert!(!file_contains!(public, "fr/blog/something/index.html", "Not rendered again"));
+}
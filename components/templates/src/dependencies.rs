@@ -0,0 +1,264 @@
+//! Finds out which templates a template needs to be rendered: the ones it extends, includes
+//! or imports macros from, recursively.
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use tera::ast::{Expr, ExprVal, FunctionCall, Node};
+use tera::{Template, Tera};
+
+#[derive(Debug, Default, Clone)]
+pub struct TemplateDependencies {
+    /// Template name -> all the templates it needs, including itself
+    templates: HashMap<String, HashSet<String>>,
+    /// Template name -> all the functions called by it or the templates it needs.
+    /// Calls with arguments also add a `name.argument` entry for each argument.
+    functions: HashMap<String, HashSet<String>>,
+}
+
+impl TemplateDependencies {
+    pub fn new(tera: &Tera) -> Self {
+        let mut direct = HashMap::new();
+        let mut direct_functions = HashMap::new();
+        for (name, template) in &tera.templates {
+            direct.insert(name.as_str(), find_direct_dependencies(template));
+            let mut functions = HashSet::new();
+            find_functions(&template.ast, &mut functions);
+            direct_functions.insert(name.as_str(), functions);
+        }
+
+        let mut templates = HashMap::new();
+        let mut functions = HashMap::new();
+        for name in tera.templates.keys() {
+            let mut seen = HashSet::new();
+            let mut stack = vec![name.as_str()];
+            while let Some(current) = stack.pop() {
+                if !seen.insert(current.to_string()) {
+                    continue;
+                }
+                if let Some(deps) = direct.get(current) {
+                    stack.extend(deps.iter().map(|d| d.as_str()));
+                }
+            }
+            let fns = seen
+                .iter()
+                .filter_map(|t| direct_functions.get(t.as_str()))
+                .flat_map(|f| f.iter().cloned())
+                .collect();
+            functions.insert(name.clone(), fns);
+            templates.insert(name.clone(), seen);
+        }
+
+        TemplateDependencies { templates, functions }
+    }
+
+    /// Whether rendering the template `name` uses any of the `changed` templates
+    pub fn depends_on(&self, name: &str, changed: &HashSet<String>) -> bool {
+        match self.templates.get(name) {
+            Some(deps) => !deps.is_disjoint(changed),
+            None => false,
+        }
+    }
+
+    /// Whether rendering the template `name` calls any of the given functions
+    pub fn calls_any(&self, name: &str, functions: &[&str]) -> bool {
+        match self.functions.get(name) {
+            Some(fns) => functions.iter().any(|f| fns.contains(*f)),
+            None => false,
+        }
+    }
+
+    /// Whether any template calls any of the given functions
+    pub fn any_calls_any(&self, functions: &[&str]) -> bool {
+        self.functions.values().any(|fns| functions.iter().any(|f| fns.contains(*f)))
+    }
+}
+
+/// The names of the templates loaded from the file at `path`.
+/// There can be several for theme templates as they are also available with their theme prefix.
+pub fn templates_from_file(tera: &Tera, path: &Path) -> HashSet<String> {
+    tera.templates
+        .values()
+        .filter(|t| t.path.as_deref().map_or(false, |p| Path::new(p) == path))
+        .map(|t| t.name.clone())
+        .collect()
+}
+
+fn find_direct_dependencies(template: &Template) -> HashSet<String> {
+    let mut deps: HashSet<String> = template.parents.iter().cloned().collect();
+    deps.extend(template.imported_macro_files.iter().map(|(file, _)| file.clone()));
+    find_includes(&template.ast, &mut deps);
+    deps
+}
+
+fn find_includes(ast: &[Node], deps: &mut HashSet<String>) {
+    for node in ast {
+        match node {
+            Node::Include(_, names, _) => deps.extend(names.iter().cloned()),
+            Node::Block(_, block, _) => find_includes(&block.body, deps),
+            Node::MacroDefinition(_, definition, _) => find_includes(&definition.body, deps),
+            Node::FilterSection(_, section, _) => find_includes(&section.body, deps),
+            Node::Forloop(_, forloop, _) => {
+                find_includes(&forloop.body, deps);
+                if let Some(ref body) = forloop.empty_body {
+                    find_includes(body, deps);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, _, body) in &condition.conditions {
+                    find_includes(body, deps);
+                }
+                if let Some((_, ref body)) = condition.otherwise {
+                    find_includes(body, deps);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn find_functions(ast: &[Node], functions: &mut HashSet<String>) {
+    for node in ast {
+        match node {
+            Node::VariableBlock(_, expr) => find_functions_in_expr(expr, functions),
+            Node::Set(_, set) => find_functions_in_expr(&set.value, functions),
+            Node::Block(_, block, _) => find_functions(&block.body, functions),
+            Node::MacroDefinition(_, definition, _) => {
+                for expr in definition.args.values().flatten() {
+                    find_functions_in_expr(expr, functions);
+                }
+                find_functions(&definition.body, functions);
+            }
+            Node::FilterSection(_, section, _) => {
+                for expr in section.filter.args.values() {
+                    find_functions_in_expr(expr, functions);
+                }
+                find_functions(&section.body, functions);
+            }
+            Node::Forloop(_, forloop, _) => {
+                find_functions_in_expr(&forloop.container, functions);
+                find_functions(&forloop.body, functions);
+                if let Some(ref body) = forloop.empty_body {
+                    find_functions(body, functions);
+                }
+            }
+            Node::If(condition, _) => {
+                for (_, expr, body) in &condition.conditions {
+                    find_functions_in_expr(expr, functions);
+                    find_functions(body, functions);
+                }
+                if let Some((_, ref body)) = condition.otherwise {
+                    find_functions(body, functions);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn find_functions_in_call(call: &FunctionCall, functions: &mut HashSet<String>) {
+    for (arg, expr) in &call.args {
+        find_functions_in_expr(expr, functions);
+        functions.insert(format!("{}.{}", call.name, arg));
+    }
+    functions.insert(call.name.clone());
+}
+
+fn find_functions_in_expr(expr: &Expr, functions: &mut HashSet<String>) {
+    for filter in &expr.filters {
+        for arg in filter.args.values() {
+            find_functions_in_expr(arg, functions);
+        }
+    }
+
+    match &expr.val {
+        ExprVal::FunctionCall(call) => find_functions_in_call(call, functions),
+        ExprVal::MacroCall(call) => {
+            for arg in call.args.values() {
+                find_functions_in_expr(arg, functions);
+            }
+        }
+        ExprVal::Math(e) => {
+            find_functions_in_expr(&e.lhs, functions);
+            find_functions_in_expr(&e.rhs, functions);
+        }
+        ExprVal::Logic(e) => {
+            find_functions_in_expr(&e.lhs, functions);
+            find_functions_in_expr(&e.rhs, functions);
+        }
+        ExprVal::In(e) => {
+            find_functions_in_expr(&e.lhs, functions);
+            find_functions_in_expr(&e.rhs, functions);
+        }
+        ExprVal::Test(test) => {
+            for arg in &test.args {
+                find_functions_in_expr(arg, functions);
+            }
+        }
+        ExprVal::Array(items) => {
+            for item in items {
+                find_functions_in_expr(item, functions);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tera::Tera;
+
+    use super::TemplateDependencies;
+
+    fn changed(names: &[&str]) -> HashSet<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn can_find_dependencies() {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("base.html", r#"{% import "macros.html" as m %}{% block c %}{% endblock %}"#),
+            ("macros.html", r#"{% macro hey() %}{% include "inner.html" %}{% endmacro %}"#),
+            ("inner.html", "inner"),
+            ("nav.html", "nav"),
+            (
+                "page.html",
+                r#"{% extends "base.html" %}{% block c %}{% if true %}{% include "nav.html" %}{% endif %}{% endblock %}"#,
+            ),
+            ("other.html", "other"),
+        ])
+        .unwrap();
+        let deps = TemplateDependencies::new(&tera);
+
+        for name in &["page.html", "base.html", "macros.html", "inner.html", "nav.html"] {
+            assert!(deps.depends_on("page.html", &changed(&[name])), "{}", name);
+        }
+        assert!(!deps.depends_on("page.html", &changed(&["other.html"])));
+        assert!(!deps.depends_on("base.html", &changed(&["nav.html"])));
+        assert!(!deps.depends_on("missing.html", &changed(&["nav.html"])));
+    }
+
+    #[test]
+    fn can_find_function_calls() {
+        let mut tera = Tera::default();
+        tera.add_raw_templates(vec![
+            ("base.html", r#"{% block c %}{{ get_url(path="a", cachebust=true) }}{% endblock %}"#),
+            (
+                "page.html",
+                r#"{% extends "base.html" %}{% block c %}{% for p in get_section(path="a") %}{% endfor %}{% endblock %}"#,
+            ),
+            ("other.html", r#"{{ 1 + now() | date }}"#),
+        ])
+        .unwrap();
+        let deps = TemplateDependencies::new(&tera);
+
+        assert!(deps.calls_any("page.html", &["get_section"]));
+        assert!(deps.calls_any("page.html", &["get_url.cachebust"]));
+        assert!(!deps.calls_any("base.html", &["get_section"]));
+        assert!(deps.calls_any("other.html", &["now"]));
+        assert!(deps.any_calls_any(&["now"]));
+        assert!(!deps.any_calls_any(&["load_data"]));
+    }
+}
@@ -1,7 +1,7 @@
 use library::{Library, Taxonomy};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use tera::{from_value, to_value, Function as TeraFn, Result, Value};
 use utils::slugs::{slugify_paths, SlugifyStrategy};
 
@@ -75,10 +75,18 @@ impl TeraFn for GetTaxonomyUrl {
 pub struct GetPage {
     base_path: PathBuf,
     library: Arc<RwLock<Library>>,
+    partial_build: bool,
+    warned: Mutex<HashSet<String>>,
 }
 impl GetPage {
-    pub fn new(base_path: PathBuf, library: Arc<RwLock<Library>>) -> Self {
-        Self { base_path: base_path.join("content"), library }
+    /// `partial_build` is whether only part of the content is loaded, see `not_loaded`
+    pub fn new(base_path: PathBuf, library: Arc<RwLock<Library>>, partial_build: bool) -> Self {
+        Self {
+            base_path: base_path.join("content"),
+            library,
+            partial_build,
+            warned: Mutex::new(HashSet::new()),
+        }
     }
 }
 impl TeraFn for GetPage {
@@ -92,6 +100,9 @@ impl TeraFn for GetPage {
         let library = self.library.read().unwrap();
         match library.get_page(&full_path) {
             Some(p) => Ok(to_value(p.to_serialized(&library)).unwrap()),
+            None if self.partial_build && full_path.exists() => {
+                Ok(not_loaded("get_page", &path, &self.warned))
+            }
             None => Err(format!("Page `{}` not found.", path).into()),
         }
     }
@@ -101,10 +112,18 @@ impl TeraFn for GetPage {
 pub struct GetSection {
     base_path: PathBuf,
     library: Arc<RwLock<Library>>,
+    partial_build: bool,
+    warned: Mutex<HashSet<String>>,
 }
 impl GetSection {
-    pub fn new(base_path: PathBuf, library: Arc<RwLock<Library>>) -> Self {
-        Self { base_path: base_path.join("content"), library }
+    /// `partial_build` is whether only part of the content is loaded, see `not_loaded`
+    pub fn new(base_path: PathBuf, library: Arc<RwLock<Library>>, partial_build: bool) -> Self {
+        Self {
+            base_path: base_path.join("content"),
+            library,
+            partial_build,
+            warned: Mutex::new(HashSet::new()),
+        }
     }
 }
 impl TeraFn for GetSection {
@@ -130,11 +149,27 @@ impl TeraFn for GetSection {
                     Ok(to_value(s.to_serialized(&library)).unwrap())
                 }
             }
+            None if self.partial_build && full_path.exists() => {
+                Ok(not_loaded("get_section", &path, &self.warned))
+            }
             None => Err(format!("Section `{}` not found.", path).into()),
         }
     }
 }
 
+/// In a partial build (`--only`), the content outside of the paths being built is not loaded.
+/// Rather than failing the build, `get_page` and `get_section` return `null` for it and we warn
+/// about it once per path.
+fn not_loaded(function: &str, path: &str, warned: &Mutex<HashSet<String>>) -> Value {
+    if warned.lock().unwrap().insert(path.to_string()) {
+        eprintln!(
+            "Warning: `{}` is not part of the content being built, `{}` returned nothing for it",
+            path, function
+        );
+    }
+    Value::Null
+}
+
 #[derive(Debug)]
 pub struct GetTaxonomy {
     library: Arc<RwLock<Library>>,
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use std::{fs, io, result};
 
 use crate::global_fns::helpers::search_for_file;
@@ -32,6 +33,8 @@ pub struct GetUrl {
     config: Config,
     permalinks: HashMap<String, String>,
     output_path: PathBuf,
+    /// Original path -> fingerprinted path of the files written under a fingerprinted name
+    assets: Arc<RwLock<HashMap<String, String>>>,
 }
 
 impl GetUrl {
@@ -40,8 +43,9 @@ impl GetUrl {
         config: Config,
         permalinks: HashMap<String, String>,
         output_path: PathBuf,
+        assets: Arc<RwLock<HashMap<String, String>>>,
     ) -> Self {
-        Self { base_path, config, permalinks, output_path }
+        Self { base_path, config, permalinks, output_path, assets }
     }
 }
 
@@ -110,13 +114,17 @@ impl TeraFn for GetUrl {
             segments.push(path);
 
             let path_with_lang = segments.join("/");
+            let fingerprinted =
+                self.assets.read().unwrap().get(path_with_lang.trim_start_matches('/')).cloned();
 
-            let mut permalink = self.config.make_permalink(&path_with_lang);
+            let mut permalink =
+                self.config.make_permalink(fingerprinted.as_deref().unwrap_or(&path_with_lang));
             if !trailing_slash && permalink.ends_with('/') {
                 permalink.pop(); // Removes the slash
             }
 
-            if cachebust {
+            // The name of a fingerprinted file already changes with its content
+            if cachebust && fingerprinted.is_none() {
                 match search_for_file(
                     &self.base_path,
                     &path_with_lang,
@@ -167,10 +175,17 @@ pub struct GetFileHash {
     base_path: PathBuf,
     theme: Option<String>,
     output_path: PathBuf,
+    /// Original path -> fingerprinted path of the files written under a fingerprinted name
+    assets: Arc<RwLock<HashMap<String, String>>>,
 }
 impl GetFileHash {
-    pub fn new(base_path: PathBuf, theme: Option<String>, output_path: PathBuf) -> Self {
-        Self { base_path, theme, output_path }
+    pub fn new(
+        base_path: PathBuf,
+        theme: Option<String>,
+        output_path: PathBuf,
+        assets: Arc<RwLock<HashMap<String, String>>>,
+    ) -> Self {
+        Self { base_path, theme, output_path, assets }
     }
 }
 
@@ -194,15 +209,22 @@ impl TeraFn for GetFileHash {
         )
         .unwrap_or(true);
 
-        let file_path =
-            match search_for_file(&self.base_path, &path, &self.theme, &self.output_path)
-                .map_err(|e| format!("`get_file_hash`: {}", e))?
-            {
-                Some((f, _)) => f,
-                None => {
-                    return Err(format!("`get_file_hash`: Cannot find file: {}", path).into());
-                }
-            };
+        let search = |path: &str| {
+            search_for_file(&self.base_path, path, &self.theme, &self.output_path)
+                .map_err(|e| format!("`get_file_hash`: {}", e))
+        };
+        // Compiled Sass files only exist under their fingerprinted name
+        let fingerprinted = self.assets.read().unwrap().get(path.trim_start_matches('/')).cloned();
+        let found = match (search(&path)?, fingerprinted) {
+            (None, Some(fingerprinted)) => search(&fingerprinted)?,
+            (found, _) => found,
+        };
+        let file_path = match found {
+            Some((f, _)) => f,
+            None => {
+                return Err(format!("`get_file_hash`: Cannot find file: {}", path).into());
+            }
+        };
 
         let f = match std::fs::File::open(file_path) {
             Ok(f) => f,
@@ -232,6 +254,7 @@ mod tests {
     use std::collections::HashMap;
     use std::fs::create_dir;
     use std::path::PathBuf;
+    use std::sync::{Arc, RwLock};
 
     use tempfile::{tempdir, TempDir};
     use tera::{to_value, Function};
@@ -265,6 +288,7 @@ title = "A title"
             Config::default(),
             HashMap::new(),
             PathBuf::new(),
+            Default::default(),
         );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
@@ -272,6 +296,27 @@ title = "A title"
         assert_eq!(static_fn.call(&args).unwrap(), "http://a-website.com/app.css?h=572e691dc68c3fcd653ae463261bdb38f35dc6f01715d9ce68799319dd158840");
     }
 
+    #[test]
+    fn can_link_to_fingerprinted_file() {
+        let dir = create_temp_dir();
+        let assets = Arc::new(RwLock::new(HashMap::new()));
+        assets
+            .write()
+            .unwrap()
+            .insert("app.css".to_string(), "app.572e691dc68c3fcd.css".to_string());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            Config::default(),
+            HashMap::new(),
+            PathBuf::new(),
+            assets,
+        );
+        let mut args = HashMap::new();
+        args.insert("path".to_string(), to_value("/app.css").unwrap());
+        args.insert("cachebust".to_string(), to_value(true).unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), "http://a-website.com/app.572e691dc68c3fcd.css");
+    }
+
     #[test]
     fn can_add_trailing_slashes() {
         let dir = create_temp_dir();
@@ -280,6 +325,7 @@ title = "A title"
             Config::default(),
             HashMap::new(),
             PathBuf::new(),
+            Default::default(),
         );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
@@ -295,6 +341,7 @@ title = "A title"
             Config::default(),
             HashMap::new(),
             PathBuf::new(),
+            Default::default(),
         );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
@@ -311,6 +358,7 @@ title = "A title"
             Config::default(),
             HashMap::new(),
             PathBuf::new(),
+            Default::default(),
         );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
@@ -329,8 +377,13 @@ title = "A title"
         create_file(&public.join("style.css"), "// Hello world")
             .expect("Failed to create file in output directory");
 
-        let static_fn =
-            GetUrl::new(dir.path().to_path_buf(), Config::default(), HashMap::new(), public);
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            Config::default(),
+            HashMap::new(),
+            public,
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("style.css").unwrap());
         assert_eq!(static_fn.call(&args).unwrap(), "http://a-website.com/style.css");
@@ -340,8 +393,13 @@ title = "A title"
     fn error_when_language_not_available() {
         let config = Config::parse(CONFIG_DATA).unwrap();
         let dir = create_temp_dir();
-        let static_fn =
-            GetUrl::new(dir.path().to_path_buf(), config, HashMap::new(), PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config,
+            HashMap::new(),
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("@/a_section/a_page.md").unwrap());
         args.insert("lang".to_string(), to_value("it").unwrap());
@@ -365,7 +423,13 @@ title = "A title"
         );
         let config = Config::parse(CONFIG_DATA).unwrap();
         let dir = create_temp_dir();
-        let static_fn = GetUrl::new(dir.path().to_path_buf(), config, permalinks, PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config,
+            permalinks,
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("@/a_section/a_page.md").unwrap());
         args.insert("lang".to_string(), to_value("fr").unwrap());
@@ -388,7 +452,13 @@ title = "A title"
             "https://remplace-par-ton-url.fr/en/a_section/a_page/".to_string(),
         );
         let dir = create_temp_dir();
-        let static_fn = GetUrl::new(dir.path().to_path_buf(), config, permalinks, PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config,
+            permalinks,
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("@/a_section/a_page.md").unwrap());
         args.insert("lang".to_string(), to_value("en").unwrap());
@@ -402,8 +472,13 @@ title = "A title"
     fn can_get_feed_url_with_default_language() {
         let config = Config::parse(CONFIG_DATA).unwrap();
         let dir = create_temp_dir();
-        let static_fn =
-            GetUrl::new(dir.path().to_path_buf(), config.clone(), HashMap::new(), PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config.clone(),
+            HashMap::new(),
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value(config.feed_filename).unwrap());
         args.insert("lang".to_string(), to_value("fr").unwrap());
@@ -414,8 +489,13 @@ title = "A title"
     fn can_get_feed_url_with_other_language() {
         let config = Config::parse(CONFIG_DATA).unwrap();
         let dir = create_temp_dir();
-        let static_fn =
-            GetUrl::new(dir.path().to_path_buf(), config.clone(), HashMap::new(), PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config.clone(),
+            HashMap::new(),
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value(config.feed_filename).unwrap());
         args.insert("lang".to_string(), to_value("en").unwrap());
@@ -425,7 +505,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha256_no_base64() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         args.insert("sha_type".to_string(), to_value(256).unwrap());
@@ -439,7 +519,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha256_base64() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         args.insert("sha_type".to_string(), to_value(256).unwrap());
@@ -450,7 +530,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha384_no_base64() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         args.insert("base64".to_string(), to_value(false).unwrap());
@@ -463,7 +543,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha384() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         assert_eq!(
@@ -475,7 +555,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha512_no_base64() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         args.insert("sha_type".to_string(), to_value(512).unwrap());
@@ -489,7 +569,7 @@ title = "A title"
     #[test]
     fn can_get_file_hash_sha512() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("app.css").unwrap());
         args.insert("sha_type".to_string(), to_value(512).unwrap());
@@ -503,8 +583,13 @@ title = "A title"
     fn can_resolve_asset_path_to_valid_url() {
         let config = Config::parse(CONFIG_DATA).unwrap();
         let dir = create_temp_dir();
-        let static_fn =
-            GetUrl::new(dir.path().to_path_buf(), config, HashMap::new(), PathBuf::new());
+        let static_fn = GetUrl::new(
+            dir.path().to_path_buf(),
+            config,
+            HashMap::new(),
+            PathBuf::new(),
+            Default::default(),
+        );
         let mut args = HashMap::new();
         args.insert(
             "path".to_string(),
@@ -523,7 +608,7 @@ title = "A title"
     #[test]
     fn error_when_file_not_found_for_hash() {
         let dir = create_temp_dir();
-        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new());
+        let static_fn = GetFileHash::new(dir.into_path(), None, PathBuf::new(), Default::default());
         let mut args = HashMap::new();
         args.insert("path".to_string(), to_value("doesnt-exist").unwrap());
         let err = format!("{}", static_fn.call(&args).unwrap_err());
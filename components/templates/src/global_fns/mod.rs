@@ -7,9 +7,11 @@ mod helpers;
 mod i18n;
 mod images;
 mod load_data;
+mod time;
 
 pub use self::content::{GetPage, GetSection, GetTaxonomy, GetTaxonomyUrl};
 pub use self::files::{GetFileHash, GetUrl};
 pub use self::i18n::Trans;
 pub use self::images::{GetImageMetadata, ResizeImage};
 pub use self::load_data::LoadData;
+pub use self::time::Now;
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use chrono::{TimeZone, Utc};
+use tera::{from_value, to_value, Function as TeraFn, Result, Value};
+
+/// Replaces the Tera `now` function when doing reproducible builds: it always
+/// returns the same, given, time instead of the current one.
+#[derive(Debug)]
+pub struct Now {
+    /// Unix timestamp, in seconds
+    timestamp: i64,
+}
+impl Now {
+    pub fn new(timestamp: i64) -> Self {
+        Self { timestamp }
+    }
+}
+impl TeraFn for Now {
+    fn call(&self, args: &HashMap<String, Value>) -> Result<Value> {
+        let timestamp = optional_arg!(
+            bool,
+            args.get("timestamp"),
+            "`now`: `timestamp` must be a boolean (true or false)."
+        )
+        .unwrap_or(false);
+        // The time is always in UTC so that it doesn't depend on the machine doing the build
+        optional_arg!(bool, args.get("utc"), "`now`: `utc` must be a boolean (true or false).");
+
+        if timestamp {
+            return Ok(to_value(self.timestamp).unwrap());
+        }
+        Ok(to_value(Utc.timestamp(self.timestamp, 0).to_rfc3339()).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn always_returns_the_given_time() {
+        let static_fn = Now::new(1_600_000_000);
+        let mut args = HashMap::new();
+        assert_eq!(static_fn.call(&args).unwrap(), "2020-09-13T12:26:40+00:00");
+
+        args.insert("timestamp".to_string(), to_value(true).unwrap());
+        assert_eq!(static_fn.call(&args).unwrap(), 1_600_000_000);
+
+        args.insert("timestamp".to_string(), to_value("yes").unwrap());
+        assert!(static_fn.call(&args).is_err());
+    }
+}
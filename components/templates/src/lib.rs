@@ -1,3 +1,4 @@
+pub mod dependencies;
 pub mod filters;
 pub mod global_fns;
 
@@ -24,6 +25,7 @@ lazy_static! {
                 include_str!("builtins/split_sitemap_index.xml"),
             ),
             ("__zola_builtins/anchor-link.html", include_str!("builtins/anchor-link.html")),
+            ("__zola_builtins/admonition.html", include_str!("builtins/admonition.html")),
             (
                 "__zola_builtins/shortcodes/youtube.html",
                 include_str!("builtins/shortcodes/youtube.html"),
@@ -1,5 +1,5 @@
 use filetime::{set_file_mtime, FileTime};
-use std::fs::{copy, create_dir_all, metadata, File};
+use std::fs::{copy, create_dir_all, metadata, remove_dir, remove_file, File};
 use std::io::prelude::*;
 use std::path::Path;
 use std::time::SystemTime;
@@ -145,6 +145,21 @@ pub fn copy_directory(src: &Path, dest: &Path, hard_link: bool) -> Result<()> {
     Ok(())
 }
 
+/// Deletes a file and then its parent directories, up to `root`, if they end up empty
+pub fn remove_file_and_empty_parents(path: &Path, root: &Path) -> Result<()> {
+    remove_file(path)
+        .map_err(|e| Error::chain(format!("Failed to delete file {}", path.display()), e))?;
+    let mut parent = path.parent();
+    while let Some(dir) = parent {
+        // Fails if the directory is not empty, which is what we want
+        if dir == root || remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+    Ok(())
+}
+
 pub fn get_file_time(path: &Path) -> Option<SystemTime> {
     path.metadata().ok().and_then(|meta| {
         Some(match (meta.created().ok(), meta.modified().ok()) {
@@ -51,6 +51,33 @@ pub fn build_cli() -> App<'static, 'static> {
                         .long("drafts")
                         .takes_value(false)
                         .help("Include drafts when loading the site"),
+                    Arg::with_name("incremental")
+                        .long("incremental")
+                        .takes_value(false)
+                        .help("Only render what changed since the last build, using a cache stored in `.zola-cache`"),
+                    Arg::with_name("report")
+                        .long("report")
+                        .takes_value(true)
+                        .help("Writes a JSON report of the build (timings, counts, output files and warnings) to the given path"),
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .takes_value(false)
+                        .help("Watch the site for changes and build it again on every change, without starting a web server"),
+                    Arg::with_name("only")
+                        .long("only")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PATH")
+                        .help("Only load and render the content in that path of the `content` directory, relative to the site root (eg `content/blog`). Can be repeated"),
+                    Arg::with_name("sync")
+                        .long("sync")
+                        .takes_value(false)
+                        .help("Only update the files that changed in the output directory and delete the ones not generated anymore, instead of emptying it"),
+                    Arg::with_name("reproducible")
+                        .long("reproducible")
+                        .takes_value(false)
+                        .help("Output exactly the same files for the same input: `now()` returns the time in SOURCE_DATE_EPOCH (or 0) instead of the current time"),
                 ]),
             SubCommand::with_name("serve")
                 .about("Serve the site. Rebuild and reload on change automatically")
@@ -59,7 +86,9 @@ pub fn build_cli() -> App<'static, 'static> {
                         .short("i")
                         .long("interface")
                         .takes_value(true)
-                        .help("Interface to bind on (default: 127.0.0.1)"),
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Interface to bind on, IPv4 or IPv6. Can be repeated to bind on several interfaces (default: 127.0.0.1)"),
                     Arg::with_name("port")
                         .short("p")
                         .long("port")
@@ -74,11 +103,16 @@ pub fn build_cli() -> App<'static, 'static> {
                         .short("u")
                         .long("base-url")
                         .takes_value(true)
-                        .help("Changes the base_url (default: 127.0.0.1)"),
+                        .help("Changes the base_url (default: 127.0.0.1, or the first interface if 127.0.0.1 is not one of them)"),
                     Arg::with_name("drafts")
                         .long("drafts")
                         .takes_value(false)
                         .help("Include drafts when loading the site"),
+                    Arg::with_name("as_of")
+                        .long("as-of")
+                        .takes_value(true)
+                        .value_name("DATE")
+                        .help("Preview the site as it will be at that date (eg `2026-12-01`), according to the `publish_date` and `expiry_date` of the pages"),
                     Arg::with_name("open")
                         .short("O")
                         .long("open")
@@ -89,6 +123,37 @@ pub fn build_cli() -> App<'static, 'static> {
                         .long("fast")
                         .takes_value(false)
                         .help("Only rebuild the minimum on change - useful when working on a specific page/section"),
+                    Arg::with_name("only")
+                        .long("only")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PATH")
+                        .help("Only load and render the content in that path of the `content` directory, relative to the site root (eg `content/blog`). Can be repeated"),
+                    Arg::with_name("https")
+                        .long("https")
+                        .takes_value(false)
+                        .help("Serve the site and the live reload over TLS, with a self-signed certificate unless `--cert` and `--key` are given"),
+                    Arg::with_name("cert")
+                        .long("cert")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .requires_all(&["https", "key"])
+                        .help("The PEM encoded certificate to use with `--https`"),
+                    Arg::with_name("key")
+                        .long("key")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .requires_all(&["https", "cert"])
+                        .help("The PEM encoded private key of the certificate given with `--cert`"),
+                    Arg::with_name("keep_output")
+                        .long("keep-output")
+                        .takes_value(false)
+                        .help("Keep the files written to the output directory when stopping instead of deleting them"),
+                    Arg::with_name("access_log")
+                        .long("access-log")
+                        .takes_value(false)
+                        .help("Print the method, path and status of every request, where the response comes from and how long it took"),
                 ]),
             SubCommand::with_name("check")
                 .about("Try building the project without rendering it. Checks links")
@@ -1,22 +1,36 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use errors::{Error, Result};
+use chrono::prelude::*;
+use notify::DebouncedEvent;
+
+use errors::{bail, Error, Result};
+use site::cache::CACHE_DIR;
 use site::Site;
 
+use crate::cmd::watch::{detect_change_kind, is_relevant_change, watch_site, ChangeKind};
 use crate::console;
 use crate::prompt::ask_bool_timeout;
 
 const BUILD_PROMPT_TIMEOUT_MILLIS: u64 = 10_000;
 
+#[allow(clippy::too_many_arguments)]
 pub fn build(
     root_dir: &Path,
     config_file: &Path,
     base_url: Option<&str>,
     output_dir: Option<&Path>,
     include_drafts: bool,
+    incremental: bool,
+    report: Option<&Path>,
+    watch: bool,
+    only: &[&str],
+    reproducible: bool,
+    sync: bool,
 ) -> Result<()> {
-    let mut site = Site::new(root_dir, config_file)?;
-    if let Some(output_dir) = output_dir {
+    let reproducible_timestamp = get_reproducible_timestamp(reproducible)?;
+    // The output directory is not emptied when syncing so no need to ask
+    if let Some(output_dir) = output_dir.filter(|_| !sync) {
         // Check whether output directory exists or not
         // This way we don't replace already existing files.
         if output_dir.exists() {
@@ -37,17 +51,132 @@ pub fn build(
                 ));
             }
         }
+    }
+
+    let build_site = || -> Result<Site> {
+        let mut site = Site::new(root_dir, config_file)?;
+        if let Some(output_dir) = output_dir {
+            site.set_output_path(output_dir);
+        }
+        if let Some(b) = base_url {
+            site.set_base_url(b.to_string());
+        }
+        if include_drafts {
+            site.include_drafts();
+        }
+        if !only.is_empty() {
+            site.set_only_paths(only)?;
+            console::warn(&format!("Partial build: only loading {}", only.join(", ")));
+        }
+        if let Some(timestamp) = reproducible_timestamp {
+            site.set_reproducible(timestamp);
+        }
+        if sync {
+            site.enable_output_sync(root_dir.join(CACHE_DIR));
+        }
+        if incremental {
+            site.enable_build_cache(root_dir.join(CACHE_DIR), env!("CARGO_PKG_VERSION"));
+        }
+        if report.is_some() {
+            site.enable_build_report();
+        }
+        site.load()?;
+        console::notify_site_size(&site);
+        console::warn_about_ignored_pages(&site);
+        site.build()?;
+
+        if let (Some(path), Some(build_report)) = (report, site.build_report()) {
+            build_report.write(path, site.final_output_path())?;
+            console::info(&format!("Build report written to {}", path.display()));
+        }
+        Ok(site)
+    };
 
-        site.set_output_path(output_dir);
+    let site = build_site()?;
+    if !watch {
+        return Ok(());
     }
-    if let Some(b) = base_url {
-        site.set_base_url(b.to_string());
+
+    let config_path = PathBuf::from(config_file);
+    let (rx, _watcher, watchers) = watch_site(root_dir, &config_path, &site)?;
+    let mut ignored_content_globset = site.config.ignored_content_globset.clone();
+    println!("Listening for changes in {}{{{}}}", root_dir.display(), watchers.join(", "));
+    println!("Press Ctrl+C to stop\n");
+
+    loop {
+        let path = match rx.recv() {
+            // Intellij does weird things on edit, chmod is there to count those changes
+            // https://github.com/passcod/notify/issues/150#issuecomment-494912080
+            Ok(DebouncedEvent::Rename(_, path))
+            | Ok(DebouncedEvent::Create(path))
+            | Ok(DebouncedEvent::Write(path))
+            | Ok(DebouncedEvent::Remove(path))
+            | Ok(DebouncedEvent::Chmod(path)) => path,
+            Ok(_) => continue,
+            Err(e) => return Err(Error::chain("Stopped watching for changes", e)),
+        };
+        if !is_relevant_change(&ignored_content_globset, &path) {
+            continue;
+        }
+
+        println!("Change detected @ {}", Local::now().format("%Y-%m-%d %H:%M:%S"));
+        let msg = match detect_change_kind(root_dir, &path, &config_path) {
+            (ChangeKind::Content, _) => "Content changed",
+            (ChangeKind::Templates, _) => "Template changed",
+            (ChangeKind::Themes, _) => "Theme changed",
+            (ChangeKind::StaticFiles, _) => "Static file changed",
+            (ChangeKind::Sass, _) => "Sass file changed",
+            (ChangeKind::Config, _) => "Config changed",
+        };
+        console::info(&format!("-> {} {}", msg, path.display()));
+
+        // Everything is written to disk so we always do a full build
+        let start = Instant::now();
+        match build_site() {
+            Ok(site) => {
+                ignored_content_globset = site.config.ignored_content_globset.clone();
+                console::report_elapsed_time(start);
+            }
+            Err(e) => console::unravel_errors("Failed to build the site", &e),
+        }
     }
-    if include_drafts {
-        site.include_drafts();
+}
+
+/// The time to use for `now()` if the build should be reproducible, which it is either when
+/// asked for or when `SOURCE_DATE_EPOCH` is set, as described on https://reproducible-builds.org
+fn get_reproducible_timestamp(reproducible: bool) -> Result<Option<i64>> {
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => parse_source_date_epoch(&value).map(Some),
+        Err(_) if reproducible => Ok(Some(0)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The timestamp needs to be one `now()` can turn into a date
+fn parse_source_date_epoch(value: &str) -> Result<i64> {
+    let timestamp = value.trim().parse::<i64>().map_err(|e| {
+        Error::chain(format!("SOURCE_DATE_EPOCH is not a valid Unix timestamp: `{}`", value), e)
+    })?;
+    if Utc.timestamp_opt(timestamp, 0).single().is_none() {
+        bail!("SOURCE_DATE_EPOCH is out of the range of supported dates: `{}`", value);
+    }
+    Ok(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_source_date_epoch;
+
+    #[test]
+    fn can_parse_source_date_epoch() {
+        assert_eq!(parse_source_date_epoch("1600000000").unwrap(), 1_600_000_000);
+        assert_eq!(parse_source_date_epoch(" 0\n").unwrap(), 0);
+    }
+
+    #[test]
+    fn errors_on_invalid_source_date_epoch() {
+        assert!(parse_source_date_epoch("yesterday").is_err());
+        assert!(parse_source_date_epoch(&i64::MAX.to_string()).is_err());
+        assert!(parse_source_date_epoch(&i64::MIN.to_string()).is_err());
     }
-    site.load()?;
-    console::notify_site_size(&site);
-    console::warn_about_ignored_pages(&site);
-    site.build()
 }
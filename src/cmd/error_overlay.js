@@ -0,0 +1,77 @@
+// Shows the build errors sent by `zola serve` over the livereload websocket in an overlay.
+// The `error` command is not part of the livereload protocol so it is handled here, before
+// the message reaches the livereload client.
+(function () {
+    var OVERLAY_ID = "zola-error-overlay";
+
+    function removeOverlay() {
+        var overlay = document.getElementById(OVERLAY_ID);
+        if (overlay) {
+            overlay.parentNode.removeChild(overlay);
+        }
+    }
+
+    function element(tag, style, text) {
+        var el = document.createElement(tag);
+        el.setAttribute("style", style);
+        if (text !== undefined) {
+            el.textContent = text;
+        }
+        return el;
+    }
+
+    function showOverlay(error) {
+        removeOverlay();
+        var overlay = element(
+            "div",
+            "position:fixed;top:0;left:0;right:0;bottom:0;z-index:2147483647;overflow:auto;" +
+            "padding:2em;background:rgba(20,20,20,0.95);color:#eee;" +
+            "font:14px/1.5 ui-monospace,Menlo,Consolas,monospace;text-align:left;"
+        );
+        overlay.id = OVERLAY_ID;
+
+        var close = element(
+            "button",
+            "float:right;padding:0.2em 0.6em;border:1px solid #888;border-radius:3px;" +
+            "background:none;color:#eee;font:inherit;cursor:pointer;",
+            "Dismiss"
+        );
+        close.onclick = removeOverlay;
+        overlay.appendChild(close);
+
+        overlay.appendChild(element("h2", "margin:0 0 1em;color:#ff6b6b;font-size:1.4em;", error.message));
+        if (error.file) {
+            var location = error.file + (error.line ? ":" + error.line : "");
+            overlay.appendChild(element("p", "margin:0 0 1em;color:#ffd479;", location));
+        }
+        for (var i = 0; i < error.chain.length; i++) {
+            overlay.appendChild(element(
+                "pre",
+                "margin:0 0 0.5em;white-space:pre-wrap;word-break:break-word;",
+                (i === 0 ? "Error: " : "Reason: ") + error.chain[i]
+            ));
+        }
+        (document.body || document.documentElement).appendChild(overlay);
+    }
+
+    var connector = window.LiveReload && window.LiveReload.connector;
+    if (!connector) {
+        return;
+    }
+    var onmessage = connector._onmessage;
+    connector._onmessage = function (event) {
+        var message;
+        try {
+            message = JSON.parse(event.data);
+        } catch (e) {
+            message = null;
+        }
+        if (message && message.command === "error") {
+            return showOverlay(message);
+        }
+        if (message && message.command === "reload") {
+            removeOverlay();
+        }
+        return onmessage.call(this, event);
+    };
+})();
@@ -0,0 +1,14 @@
+// Gives livereload.js its options from the URL of its script tag. It finds them itself otherwise
+// but can't parse IPv6 hosts like `[::1]`, leaving it without a host to connect to.
+(function () {
+    var script = document.currentScript;
+    if (!script || "LiveReloadOptions" in window) {
+        return;
+    }
+    var url = new URL(script.src);
+    var options = { host: url.hostname, https: url.protocol === "https:" };
+    url.searchParams.forEach(function (value, key) {
+        options[key.replace(/-/g, "_")] = value;
+    });
+    window.LiveReloadOptions = options;
+})();
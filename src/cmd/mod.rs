@@ -1,7 +1,12 @@
 mod build;
 mod check;
 mod init;
+mod output;
+mod proxy;
 mod serve;
+mod status;
+mod tls;
+mod watch;
 
 pub use self::build::build;
 pub use self::check::check;
@@ -0,0 +1,115 @@
+//! Removal of what `zola serve` wrote to the output directory when it stops, without touching
+//! the files that were already there, since the directory can be given with `--output-dir`.
+use std::collections::HashSet;
+use std::fs::{remove_dir, remove_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use errors::{Error, Result};
+
+/// The files and directories of the output directory before the site is built in it
+#[derive(Debug)]
+pub struct OutputSnapshot {
+    root: PathBuf,
+    /// `None` if the output directory did not exist
+    paths: Option<HashSet<PathBuf>>,
+}
+
+impl OutputSnapshot {
+    pub fn take(root: &Path) -> OutputSnapshot {
+        let paths = if root.exists() {
+            Some(
+                WalkDir::new(root)
+                    .min_depth(1)
+                    .into_iter()
+                    .flatten()
+                    .map(|e| e.into_path())
+                    .collect(),
+            )
+        } else {
+            None
+        };
+        OutputSnapshot { root: root.to_path_buf(), paths }
+    }
+
+    /// Removes the files and directories created since the snapshot was taken, or the whole
+    /// output directory if it did not exist then
+    pub fn remove_created(&self) -> Result<()> {
+        let paths = match self.paths {
+            Some(ref paths) => paths,
+            None => {
+                if self.root.exists() {
+                    remove_dir_all(&self.root).map_err(|e| {
+                        Error::chain(format!("Failed to delete {}", self.root.display()), e)
+                    })?;
+                }
+                return Ok(());
+            }
+        };
+
+        // Going through the content of a directory before it so new directories are empty
+        // by the time we get to them
+        for entry in
+            WalkDir::new(&self.root).min_depth(1).contents_first(true).into_iter().flatten()
+        {
+            if paths.contains(entry.path()) {
+                continue;
+            }
+            let res = if entry.file_type().is_dir() {
+                remove_dir(entry.path())
+            } else {
+                remove_file(entry.path())
+            };
+            res.map_err(|e| {
+                Error::chain(format!("Failed to delete {}", entry.path().display()), e)
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn only_removes_created_files() {
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("public");
+        create_dir_all(root.join("docs")).unwrap();
+        write(root.join("README.md"), "readme").unwrap();
+        write(root.join("docs").join("notes.txt"), "notes").unwrap();
+
+        let snapshot = OutputSnapshot::take(&root);
+        write(root.join("README.md"), "overwritten").unwrap();
+        write(root.join("site.css"), "css").unwrap();
+        write(root.join("docs").join("logo.png"), "png").unwrap();
+        create_dir_all(root.join("images").join("icons")).unwrap();
+        write(root.join("images").join("icons").join("zola.svg"), "svg").unwrap();
+        snapshot.remove_created().unwrap();
+
+        assert!(root.join("README.md").exists());
+        assert!(root.join("docs").join("notes.txt").exists());
+        assert!(!root.join("site.css").exists());
+        assert!(!root.join("docs").join("logo.png").exists());
+        assert!(!root.join("images").exists());
+    }
+
+    #[test]
+    fn removes_output_directory_it_created() {
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("public");
+
+        let snapshot = OutputSnapshot::take(&root);
+        create_dir_all(root.join("images")).unwrap();
+        write(root.join("site.css"), "css").unwrap();
+        snapshot.remove_created().unwrap();
+
+        assert!(!root.exists());
+    }
+}
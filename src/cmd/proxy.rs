@@ -0,0 +1,200 @@
+//! Forwarding of the requests under the `serve.proxy` prefixes of the config to other servers,
+//! so the site can call a local API through `zola serve` without running into CORS issues.
+use std::collections::BTreeMap;
+
+use hyper::client::HttpConnector;
+use hyper::header::{self, HeaderMap, HeaderName};
+use hyper::{Body, Client, Request, Response, StatusCode, Uri, Version};
+
+use crate::console;
+
+/// The headers only meant for a single connection, which are not forwarded
+const HOP_BY_HOP_HEADERS: &[HeaderName] = &[
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    /// Path prefix -> upstream URL, both without trailing slash and longest prefixes first
+    routes: Vec<(String, String)>,
+    client: Client<HttpConnector>,
+}
+
+impl Proxy {
+    /// Returns `None` if there is nothing to proxy
+    pub fn new(routes: &BTreeMap<String, String>) -> Option<Proxy> {
+        if routes.is_empty() {
+            return None;
+        }
+        let mut routes: Vec<_> = routes
+            .iter()
+            .map(|(prefix, upstream)| {
+                (
+                    prefix.trim_end_matches('/').to_string(),
+                    upstream.trim_end_matches('/').to_string(),
+                )
+            })
+            .collect();
+        routes.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Some(Proxy { routes, client: Client::new() })
+    }
+
+    /// The URL to forward the request for `uri` to, if it is under one of the prefixes.
+    /// The prefix is replaced by the URL of the upstream server and the query string is kept.
+    pub fn upstream_url(&self, uri: &Uri) -> Option<String> {
+        let path = uri.path();
+        let (prefix, upstream) = self.routes.iter().find(|(prefix, _)| {
+            path == prefix
+                || path.strip_prefix(prefix.as_str()).map_or(false, |rest| rest.starts_with('/'))
+        })?;
+        let mut url = format!("{}{}", upstream, &path[prefix.len()..]);
+        if let Some(query) = uri.query() {
+            url.push('?');
+            url.push_str(query);
+        }
+        Some(url)
+    }
+
+    /// Forwards the request to `url` with its method, headers and body. The `Host` header is the
+    /// one of the upstream server, the original one being sent as `X-Forwarded-Host`.
+    /// The request is always sent over HTTP/1.1, even if the browser used HTTP/2 with `--https`.
+    pub async fn forward(&self, mut req: Request<Body>, url: &str) -> Response<Body> {
+        let uri: Uri = match url.parse() {
+            Ok(uri) => uri,
+            Err(_) => return bad_gateway(format!("Invalid proxy URL {}", url)),
+        };
+        if let Some(host) = req.headers_mut().remove(header::HOST) {
+            req.headers_mut().insert(HeaderName::from_static("x-forwarded-host"), host);
+        }
+        remove_hop_by_hop_headers(req.headers_mut());
+        *req.uri_mut() = uri;
+        *req.version_mut() = Version::HTTP_11;
+
+        match self.client.request(req).await {
+            Ok(mut response) => {
+                remove_hop_by_hop_headers(response.headers_mut());
+                response
+            }
+            Err(e) => {
+                let message = format!("Failed to proxy the request to {}: {}", url, e);
+                console::error(&message);
+                bad_gateway(message)
+            }
+        }
+    }
+}
+
+/// Removes the hop-by-hop headers, including the ones listed in the `Connection` header
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in listed.iter().chain(HOP_BY_HOP_HEADERS) {
+        headers.remove(name);
+    }
+    headers.remove("keep-alive");
+    headers.remove("proxy-connection");
+}
+
+fn bad_gateway(message: String) -> Response<Body> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain")
+        .status(StatusCode::BAD_GATEWAY)
+        .body(message.into())
+        .expect("Could not build Bad Gateway response")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::Server;
+
+    use super::*;
+
+    fn proxy() -> Proxy {
+        let mut routes = BTreeMap::new();
+        routes.insert("/api/".to_string(), "http://localhost:8080/".to_string());
+        routes.insert("/api/v2".to_string(), "http://localhost:9090/v2/".to_string());
+        Proxy::new(&routes).unwrap()
+    }
+
+    fn upstream_url(proxy: &Proxy, uri: &str) -> Option<String> {
+        proxy.upstream_url(&uri.parse().unwrap())
+    }
+
+    #[test]
+    fn is_disabled_without_routes() {
+        assert!(Proxy::new(&BTreeMap::new()).is_none());
+    }
+
+    #[test]
+    fn can_find_upstream_url() {
+        let proxy = proxy();
+        assert_eq!(
+            upstream_url(&proxy, "/api/users?page=2"),
+            Some("http://localhost:8080/users?page=2".to_string())
+        );
+        assert_eq!(upstream_url(&proxy, "/api"), Some("http://localhost:8080".to_string()));
+        // The longest prefix wins
+        assert_eq!(
+            upstream_url(&proxy, "/api/v2/users"),
+            Some("http://localhost:9090/v2/users".to_string())
+        );
+        assert_eq!(upstream_url(&proxy, "/apiary"), None);
+        assert_eq!(upstream_url(&proxy, "/posts/"), None);
+    }
+
+    #[test]
+    fn can_forward_http2_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/users", listener.local_addr().unwrap());
+        let rt = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+
+        let body = rt.block_on(async {
+            // Replies with what it received
+            let make_service = make_service_fn(|_| async {
+                Ok::<_, hyper::Error>(service_fn(|req: Request<Body>| async move {
+                    let mut names: Vec<_> = req.headers().keys().map(|k| k.as_str()).collect();
+                    names.sort_unstable();
+                    let body = format!("{:?} {} {}", req.version(), req.uri(), names.join(","));
+                    Ok::<_, hyper::Error>(
+                        Response::builder()
+                            .header(header::CONNECTION, "close")
+                            .body(Body::from(body))
+                            .unwrap(),
+                    )
+                }))
+            });
+            tokio::spawn(Server::from_tcp(listener).unwrap().serve(make_service));
+
+            let req = Request::builder()
+                .version(Version::HTTP_2)
+                .uri("/api/users")
+                .header(header::HOST, "localhost:1111")
+                .header(header::CONNECTION, "x-trace")
+                .header("x-trace", "1")
+                .header(header::TE, "trailers")
+                .header(header::ACCEPT, "application/json")
+                .body(Body::empty())
+                .unwrap();
+            let response = proxy().forward(req, &url).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(!response.headers().contains_key(header::CONNECTION));
+            hyper::body::to_bytes(response.into_body()).await.unwrap()
+        });
+
+        assert_eq!(body, "HTTP/1.1 /users accept,host,x-forwarded-host");
+    }
+}
@@ -21,64 +21,229 @@
 // OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION
 // WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
 
-use std::fs::{read_dir, remove_dir_all};
-use std::net::{SocketAddrV4, TcpListener};
+use std::error::Error as StdError;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use hyper::header;
+use hyper::header::{self, HeaderMap, HeaderName, HeaderValue};
+use hyper::http::response;
+use hyper::server::conn::Http;
 use hyper::server::Server;
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Method, Request, Response, StatusCode};
+use hyper::{Body, Method, Request, Response, StatusCode, Version};
 use mime_guess::from_path as mimetype_from_path;
 
 use chrono::prelude::*;
-use notify::{watcher, RecursiveMode, Watcher};
+use lazy_static::lazy_static;
+use tokio::io::copy_bidirectional;
+use tokio::sync::watch;
+use tokio_rustls::TlsAcceptor;
 use ws::{Message, Sender, WebSocket};
 
-use errors::{Error as ZolaError, Result};
-use globset::GlobSet;
-use pathdiff::diff_paths;
+use errors::{Error, Result};
 use relative_path::{RelativePath, RelativePathBuf};
+use site::cache::CACHE_DIR;
+use site::compress::{Compressor, Level};
+use site::headers::{ResponseRules, HEADERS_FILENAME, REDIRECTS_FILENAME};
 use site::sass::compile_sass;
-use site::{Site, SITE_CONTENT};
+use site::{Site, SITE_CONTENT, SITE_REDIRECTS};
 use utils::fs::copy_file;
 
+use crate::cmd::output::OutputSnapshot;
+use crate::cmd::proxy::Proxy;
+use crate::cmd::status::{BUILD_STATUS, STATUS_PATH};
+use crate::cmd::tls::tls_acceptor;
+use crate::cmd::watch::{detect_change_kind, is_relevant_change, watch_site, ChangeKind};
 use crate::console;
 use std::ffi::OsStr;
 
-#[derive(Debug, PartialEq)]
-enum ChangeKind {
-    Content,
-    Templates,
-    Themes,
-    StaticFiles,
-    Sass,
-    Config,
+static METHOD_NOT_ALLOWED_TEXT: &[u8] = b"Method Not Allowed";
+static NOT_FOUND_TEXT: &[u8] = b"Not Found";
+
+// This is dist/livereload.min.js from the LiveReload.js v3.2.4 release, preceded by the code
+// reading its options and followed by the overlay showing the build errors
+const LIVE_RELOAD: &str = concat!(
+    include_str!("livereload_options.js"),
+    "\n",
+    include_str!("livereload.js"),
+    "\n",
+    include_str!("error_overlay.js")
+);
+
+/// The extensions of the files we look for in errors to show where they come from
+const ERROR_FILE_EXTENSIONS: &[&str] = &["md", "html", "xml", "txt", "toml", "scss", "sass"];
+/// Where the previews with `--as-of` are written by default, relative to the cache directory
+const AS_OF_OUTPUT_DIR: &str = "as-of";
+
+lazy_static! {
+    /// The error overlay message of the last build if it failed, sent to the pages loaded after it
+    static ref BUILD_ERROR: Mutex<Option<String>> = Mutex::new(None);
 }
 
-#[derive(Debug, PartialEq)]
-enum WatchMode {
-    Required,
-    Optional,
-    Condition(bool),
+/// What the web server needs to answer the requests
+#[derive(Clone)]
+struct ServerState {
+    /// The output directory
+    root: PathBuf,
+    compressor: Option<Compressor>,
+    rules: Arc<RwLock<ResponseRules>>,
+    proxy: Option<Proxy>,
+    /// Whether to print the requests
+    access_log: bool,
 }
 
-static METHOD_NOT_ALLOWED_TEXT: &[u8] = b"Method Not Allowed";
-static NOT_FOUND_TEXT: &[u8] = b"Not Found";
+/// Where the response comes from, stored in its extensions for the access log
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ServedFrom {
+    Memory,
+    Disk,
+    Redirect,
+    Proxy,
+    Zola,
+}
+
+impl ServedFrom {
+    fn name(self) -> &'static str {
+        match self {
+            ServedFrom::Memory => "memory",
+            ServedFrom::Disk => "disk",
+            ServedFrom::Redirect => "redirect",
+            ServedFrom::Proxy => "proxy",
+            ServedFrom::Zola => "zola",
+        }
+    }
+}
+
+async fn handle_request(req: Request<Body>, state: ServerState) -> Result<Response<Body>> {
+    if !state.access_log {
+        return respond(req, &state).await;
+    }
+
+    let start = Instant::now();
+    let method = req.method().clone();
+    // HTTP/2 requests have the scheme and host in their URI
+    let path = req.uri().path_and_query().map_or("/", |p| p.as_str()).to_string();
+    let response = respond(req, &state).await?;
+    println!(
+        "{} {} {} {} {} {:.1}ms",
+        Local::now().format("%H:%M:%S"),
+        method,
+        path,
+        response.status().as_u16(),
+        response.extensions().get::<ServedFrom>().map_or("-", |s| s.name()),
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+    Ok(response)
+}
 
-// This is dist/livereload.min.js from the LiveReload.js v3.2.4 release
-const LIVE_RELOAD: &str = include_str!("livereload.js");
+async fn respond(req: Request<Body>, state: &ServerState) -> Result<Response<Body>> {
+    if req.uri().path() == STATUS_PATH {
+        return Ok(build_status(&req));
+    }
+
+    if let Some(ref proxy) = state.proxy {
+        if let Some(url) = proxy.upstream_url(req.uri()) {
+            let mut response = proxy.forward(req, &url).await;
+            response.extensions_mut().insert(ServedFrom::Proxy);
+            return Ok(response);
+        }
+    }
 
-async fn handle_request(req: Request<Body>, mut root: PathBuf) -> Result<Response<Body>> {
+    let root = state.root.clone();
+    let compressor = state.compressor.clone();
+    let rules = &state.rules;
+
+    let path =
+        percent_encoding::percent_decode_str(req.uri().path()).decode_utf8_lossy().to_string();
+    let method = req.method().clone();
+    let headers = req.headers().clone();
+
+    // Like on Netlify, redirects only apply if there is nothing at that path unless forced
+    let forced = rules
+        .read()
+        .unwrap()
+        .redirect(&path)
+        .filter(|(r, _)| r.force)
+        .map(|(r, to)| (r.status, to));
+    let mut response = match forced {
+        Some((status, to)) => {
+            apply_redirect(method, headers, root, compressor, status, &to).await?
+        }
+        None => {
+            let response = serve_request(req, root.clone(), compressor.clone()).await?;
+            let redirect = if response.status() == StatusCode::NOT_FOUND {
+                rules.read().unwrap().redirect(&path).map(|(r, to)| (r.status, to))
+            } else {
+                None
+            };
+            match redirect {
+                Some((status, to)) => {
+                    apply_redirect(method, headers, root, compressor, status, &to).await?
+                }
+                None => response,
+            }
+        }
+    };
+
+    for (name, value) in rules.read().unwrap().headers(&path) {
+        if let (Ok(name), Ok(value)) =
+            (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value))
+        {
+            response.headers_mut().insert(name, value);
+        }
+    }
+    Ok(response)
+}
+
+/// Redirects to `to` for 3xx statuses, otherwise responds with the content at `to` and `status`
+async fn apply_redirect(
+    method: Method,
+    headers: HeaderMap,
+    root: PathBuf,
+    compressor: Option<Compressor>,
+    status: u16,
+    to: &str,
+) -> Result<Response<Body>> {
+    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::MOVED_PERMANENTLY);
+    if status.is_redirection() {
+        return Ok(Response::builder()
+            .header(header::LOCATION, to)
+            .status(status)
+            .extension(ServedFrom::Redirect)
+            .body(Body::empty())
+            .expect("Could not build redirect response"));
+    }
+
+    let mut req = match Request::builder().method(method).uri(to).body(Body::empty()) {
+        Ok(req) => req,
+        Err(_) => return Ok(not_found(&root)),
+    };
+    *req.headers_mut() = headers;
+    let mut response = serve_request(req, root, compressor).await?;
+    if response.status().is_success() {
+        *response.status_mut() = status;
+    }
+    Ok(response)
+}
+
+async fn serve_request(
+    req: Request<Body>,
+    mut root: PathBuf,
+    compressor: Option<Compressor>,
+) -> Result<Response<Body>> {
     let original_root = root.clone();
     let mut path = RelativePathBuf::new();
     // https://zola.discourse.group/t/percent-encoding-for-slugs/736
     let decoded = match percent_encoding::percent_decode_str(req.uri().path()).decode_utf8() {
         Ok(d) => d,
-        Err(_) => return Ok(not_found()),
+        Err(_) => return Ok(not_found(&root)),
     };
 
     for c in decoded.split('/') {
@@ -94,8 +259,13 @@ async fn handle_request(req: Request<Body>, mut root: PathBuf) -> Result<Respons
         }
     }
 
+    // Aliases, `redirect_to` and the first page of paginated sections
+    if let Some(permalink) = SITE_REDIRECTS.read().unwrap().get(&path) {
+        return Ok(moved_permanently(permalink));
+    }
+
     if let Some(content) = SITE_CONTENT.read().unwrap().get(&path) {
-        return Ok(in_memory_content(&path, content));
+        return Ok(in_memory_content(&req, &path, content, compressor.as_ref()));
     }
 
     // Handle only `GET`/`HEAD` requests
@@ -104,9 +274,12 @@ async fn handle_request(req: Request<Body>, mut root: PathBuf) -> Result<Respons
         _ => return Ok(method_not_allowed()),
     }
 
-    // Handle only simple path requests
-    if req.uri().scheme_str().is_some() || req.uri().host().is_some() {
-        return Ok(not_found());
+    // Handle only simple path requests. HTTP/2 requests always have the scheme and host in
+    // their URI.
+    if req.version() < Version::HTTP_2
+        && (req.uri().scheme_str().is_some() || req.uri().host().is_some())
+    {
+        return Ok(not_found(&original_root));
     }
 
     // Remove the first slash from the request path
@@ -114,12 +287,12 @@ async fn handle_request(req: Request<Body>, mut root: PathBuf) -> Result<Respons
     root.push(&decoded[1..]);
 
     // Ensure we are only looking for things in our public folder
-    if !root.starts_with(original_root) {
-        return Ok(not_found());
+    if !root.starts_with(&original_root) {
+        return Ok(not_found(&original_root));
     }
 
     let metadata = match tokio::fs::metadata(root.as_path()).await {
-        Err(err) => return Ok(io_error(err)),
+        Err(err) => return Ok(io_error(err, &original_root)),
         Ok(metadata) => metadata,
     };
     if metadata.is_dir() {
@@ -130,30 +303,66 @@ async fn handle_request(req: Request<Body>, mut root: PathBuf) -> Result<Respons
     let result = tokio::fs::read(&root).await;
 
     let contents = match result {
-        Err(err) => return Ok(io_error(err)),
+        Err(err) => return Ok(io_error(err, &original_root)),
         Ok(contents) => contents,
     };
 
-    Ok(Response::builder()
+    let builder = Response::builder()
         .status(StatusCode::OK)
         .header(
             header::CONTENT_TYPE,
             mimetype_from_path(&root).first_or_octet_stream().essence_str(),
         )
         .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
-        .body(Body::from(contents))
-        .unwrap())
+        .extension(ServedFrom::Disk);
+    let (builder, contents) =
+        compress_response(&req, builder, &root, contents, compressor.as_ref());
+    Ok(builder.body(Body::from(contents)).unwrap())
+}
+
+/// Compresses the response if `compress_output` is enabled, the file is worth compressing and
+/// the client accepts one of the enabled encodings. It uses a fast level as it is done on every
+/// request.
+fn compress_response(
+    req: &Request<Body>,
+    builder: response::Builder,
+    path: &Path,
+    contents: Vec<u8>,
+    compressor: Option<&Compressor>,
+) -> (response::Builder, Vec<u8>) {
+    let compressor = match compressor {
+        Some(c) if c.should_compress(path, contents.len() as u64) => c,
+        _ => return (builder, contents),
+    };
+    let builder = builder.header(header::VARY, "Accept-Encoding");
+    let accept_encoding =
+        req.headers().get(header::ACCEPT_ENCODING).and_then(|v| v.to_str().ok()).unwrap_or("");
+    match compressor.negotiate(accept_encoding) {
+        Some(encoding) => match encoding.compress(&contents, Level::Fast) {
+            Ok(compressed) => {
+                (builder.header(header::CONTENT_ENCODING, encoding.name()), compressed)
+            }
+            Err(_) => (builder, contents),
+        },
+        None => (builder, contents),
+    }
 }
 
 fn livereload_js() -> Response<Body> {
     Response::builder()
         .header(header::CONTENT_TYPE, "text/javascript")
         .status(StatusCode::OK)
+        .extension(ServedFrom::Zola)
         .body(LIVE_RELOAD.into())
         .expect("Could not build livereload.js response")
 }
 
-fn in_memory_content(path: &RelativePathBuf, content: &str) -> Response<Body> {
+fn in_memory_content(
+    req: &Request<Body>,
+    path: &RelativePathBuf,
+    content: &str,
+    compressor: Option<&Compressor>,
+) -> Response<Body> {
     let content_type = match path.extension() {
         Some(ext) => match ext {
             "xml" => "text/xml",
@@ -162,11 +371,33 @@ fn in_memory_content(path: &RelativePathBuf, content: &str) -> Response<Body> {
         },
         None => "text/html",
     };
-    Response::builder()
+    // Pages and sections are stored without their `index.html`
+    let filename = match path.extension() {
+        Some(_) => path.to_path(""),
+        None => path.join("index.html").to_path(""),
+    };
+    let builder = Response::builder()
         .header(header::CONTENT_TYPE, content_type)
         .status(StatusCode::OK)
-        .body(content.to_owned().into())
-        .expect("Could not build HTML response")
+        .extension(ServedFrom::Memory);
+    let (builder, content) =
+        compress_response(req, builder, &filename, content.as_bytes().to_vec(), compressor);
+    builder.body(content.into()).expect("Could not build HTML response")
+}
+
+/// The status of the last build, for editor integrations
+fn build_status(req: &Request<Body>) -> Response<Body> {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return method_not_allowed();
+    }
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .status(StatusCode::OK)
+        .extension(ServedFrom::Zola)
+        .body(BUILD_STATUS.read().unwrap().to_json().to_string().into())
+        .expect("Could not build status response")
 }
 
 fn method_not_allowed() -> Response<Body> {
@@ -177,9 +408,18 @@ fn method_not_allowed() -> Response<Body> {
         .expect("Could not build Method Not Allowed response")
 }
 
-fn io_error(err: std::io::Error) -> Response<Body> {
+fn moved_permanently(location: &str) -> Response<Body> {
+    Response::builder()
+        .header(header::LOCATION, location)
+        .status(StatusCode::MOVED_PERMANENTLY)
+        .extension(ServedFrom::Redirect)
+        .body(Body::empty())
+        .expect("Could not build Moved Permanently response")
+}
+
+fn io_error(err: std::io::Error, root: &Path) -> Response<Body> {
     match err.kind() {
-        std::io::ErrorKind::NotFound => not_found(),
+        std::io::ErrorKind::NotFound => not_found(root),
         std::io::ErrorKind::PermissionDenied => {
             Response::builder().status(StatusCode::FORBIDDEN).body(Body::empty()).unwrap()
         }
@@ -187,15 +427,18 @@ fn io_error(err: std::io::Error) -> Response<Body> {
     }
 }
 
-fn not_found() -> Response<Body> {
+/// Responds with the 404 page rendered by the site, or the one found in the output directory
+/// if it comes from the static files
+fn not_found(root: &Path) -> Response<Body> {
     let not_found_path = RelativePath::new("404.html");
-    let content = SITE_CONTENT.read().unwrap().get(not_found_path).cloned();
+    let content = SITE_CONTENT.read().unwrap().get(not_found_path).cloned().map(Body::from);
+    let content = content.or_else(|| std::fs::read(root.join("404.html")).ok().map(Body::from));
 
     if let Some(body) = content {
         return Response::builder()
             .header(header::CONTENT_TYPE, "text/html")
             .status(StatusCode::NOT_FOUND)
-            .body(body.into())
+            .body(body)
             .expect("Could not build Not Found response");
     }
 
@@ -210,6 +453,7 @@ fn not_found() -> Response<Body> {
 fn rebuild_done_handling(broadcaster: &Sender, res: Result<()>, reload_path: &str) {
     match res {
         Ok(_) => {
+            *BUILD_ERROR.lock().unwrap() = None;
             broadcaster
                 .send(format!(
                     r#"
@@ -225,165 +469,420 @@ fn rebuild_done_handling(broadcaster: &Sender, res: Result<()>, reload_path: &st
                 ))
                 .unwrap();
         }
-        Err(e) => console::unravel_errors("Failed to build the site", &e),
+        Err(e) => {
+            let message = "Failed to build the site";
+            console::unravel_errors(message, &e);
+            let overlay_message = build_error_message(message, &e);
+            BUILD_STATUS.write().unwrap().set_error(error_details(message, &e));
+            broadcaster.send(overlay_message.as_str()).unwrap();
+            *BUILD_ERROR.lock().unwrap() = Some(overlay_message);
+        }
+    }
+}
+
+/// The absolute URL path of a file relative to the output directory, eg `/css/site.css`
+fn url_path(path: &Path) -> String {
+    let mut url = String::new();
+    for component in path.components() {
+        url.push('/');
+        url.push_str(&component.as_os_str().to_string_lossy());
     }
+    url
+}
+
+/// The `error` message sent over the livereload websocket for the error overlay.
+/// It is not part of the livereload protocol and handled by `error_overlay.js`.
+fn build_error_message(message: &str, error: &Error) -> String {
+    let mut details = error_details(message, error);
+    details["command"] = "error".into();
+    details.to_string()
+}
+
+/// The message of a build error along with its causes and the file and line it is about,
+/// as shown in the error overlay and the build status
+fn error_details(message: &str, error: &Error) -> serde_json::Value {
+    let mut chain = vec![error.to_string()];
+    let mut cause = error.source();
+    while let Some(e) = cause {
+        chain.push(e.to_string());
+        cause = e.source();
+    }
+    let (file, line) = error_location(&chain);
+    serde_json::json!({
+        "message": message,
+        "file": file,
+        "line": line,
+        "chain": chain,
+    })
+}
+
+/// Finds the file and the line an error is about, if any, looking at the innermost errors first.
+/// Files are the quoted paths in the messages and lines come from the template/shortcode
+/// parsing errors (` --> line:column`) and the TOML ones (`at line x`).
+fn error_location(chain: &[String]) -> (Option<String>, Option<usize>) {
+    let file = chain.iter().rev().find_map(|message| {
+        message
+            .split(['\'', '"', '`'])
+            .skip(1)
+            .step_by(2)
+            .filter(|quoted| {
+                !quoted.contains(char::is_whitespace)
+                    && Path::new(quoted)
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .map_or(false, |e| ERROR_FILE_EXTENSIONS.contains(&e))
+            })
+            .last()
+            .map(|quoted| quoted.to_string())
+    });
+    let line = chain.iter().rev().find_map(|message| {
+        ["--> ", "at line "].iter().find_map(|marker| {
+            let start = message.find(marker)? + marker.len();
+            let digits: String =
+                message[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+    });
+    (file, line)
+}
+
+/// Serves the site on `listener` until `zola serve` stops
+async fn serve_http(listener: TcpListener, state: ServerState, shutdown: watch::Receiver<bool>) {
+    let make_service = make_service_fn(move |_| {
+        let state = state.clone();
+
+        async { Ok::<_, hyper::Error>(service_fn(move |req| handle_request(req, state.clone()))) }
+    });
+
+    let server = Server::from_tcp(listener)
+        .expect("Could not start web server")
+        .serve(make_service)
+        .with_graceful_shutdown(wait_for_shutdown(shutdown));
+    server.await.expect("Could not start web server");
+}
+
+/// Same as `serve_http`, over TLS
+async fn serve_https(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    state: ServerState,
+    shutdown: watch::Receiver<bool>,
+) {
+    listener.set_nonblocking(true).expect("Could not start web server");
+    let listener = tokio::net::TcpListener::from_std(listener).expect("Could not start web server");
+    let stopping = wait_for_shutdown(shutdown);
+    tokio::pin!(stopping);
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+            _ = &mut stopping => return,
+        };
+        let acceptor = acceptor.clone();
+        let state = state.clone();
+        tokio::spawn(async move {
+            // Browsers close the connection when they don't trust the certificate
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
+                Err(_) => return,
+            };
+            let service = service_fn(move |req| handle_request(req, state.clone()));
+            let _ = Http::new().serve_connection(stream, service).await;
+        });
+    }
+}
+
+/// Forwards the connections made to `listener` to `target`, decrypting them first if given a TLS
+/// acceptor. This is how the livereload websocket, which only listens locally and handles plain
+/// connections, is served on the interfaces and over WSS.
+async fn forward_connections(
+    listener: TcpListener,
+    target: SocketAddr,
+    acceptor: Option<TlsAcceptor>,
+    shutdown: watch::Receiver<bool>,
+) {
+    listener.set_nonblocking(true).expect("Could not listen for the websocket connections");
+    let listener = tokio::net::TcpListener::from_std(listener)
+        .expect("Could not listen for the websocket connections");
+    let stopping = wait_for_shutdown(shutdown);
+    tokio::pin!(stopping);
+    loop {
+        let mut stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(_) => continue,
+            },
+            _ = &mut stopping => return,
+        };
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            let mut target_stream = match tokio::net::TcpStream::connect(target).await {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            match acceptor {
+                Some(acceptor) => {
+                    if let Ok(mut tls_stream) = acceptor.accept(stream).await {
+                        let _ = copy_bidirectional(&mut tls_stream, &mut target_stream).await;
+                    }
+                }
+                None => {
+                    let _ = copy_bidirectional(&mut stream, &mut target_stream).await;
+                }
+            }
+        });
+    }
+}
+
+/// Resolves when `zola serve` is stopping
+async fn wait_for_shutdown(mut shutdown: watch::Receiver<bool>) {
+    let _ = shutdown.changed().await;
+}
+
+/// The host names the self-signed certificate needs to be valid for
+fn tls_hosts(interfaces: &[IpAddr], base_url: &str) -> Vec<String> {
+    let mut hosts = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()];
+    // Nobody can open a page on 0.0.0.0 or ::
+    let interfaces = interfaces.iter().filter(|ip| !ip.is_unspecified()).map(|ip| ip.to_string());
+    let base_url = base_url.trim_start_matches('[').trim_end_matches(']');
+    for host in interfaces.chain(std::iter::once(base_url.to_string())) {
+        let unspecified = host.parse::<IpAddr>().map(|ip| ip.is_unspecified()).unwrap_or(false);
+        if !unspecified && !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    }
+    hosts
+}
+
+/// Parses the IPv4 and IPv6 addresses given to `--interface`
+fn parse_interfaces(interfaces: &[&str]) -> Result<Vec<IpAddr>> {
+    interfaces
+        .iter()
+        .map(|interface| {
+            let ip = interface.trim_start_matches('[').trim_end_matches(']');
+            ip.parse().map_err(|_| format!("Invalid address: {}.", interface).into())
+        })
+        .collect()
+}
+
+/// The host of the URLs of the site when `--base-url` is not given: 127.0.0.1, unless none of
+/// the interfaces accepts connections on it, in which case it is the first interface
+fn default_base_url(interfaces: &[IpAddr]) -> String {
+    let localhost =
+        interfaces.iter().any(|ip| ip.is_unspecified() || *ip == IpAddr::V4(Ipv4Addr::LOCALHOST));
+    match interfaces.first() {
+        Some(ip) if !localhost => ip.to_string(),
+        _ => "127.0.0.1".to_string(),
+    }
+}
+
+/// The output directory to use instead of the one of the site, if any.
+/// The previews with `--as-of` get one per port when none is given, so that previews running at
+/// the same time don't delete the files of each other when they stop.
+fn output_path(
+    root_dir: &Path,
+    output_dir: Option<&Path>,
+    as_of: Option<&str>,
+    interface_port: u16,
+) -> Option<PathBuf> {
+    match (output_dir, as_of) {
+        (Some(output_dir), _) => Some(output_dir.to_path_buf()),
+        (None, Some(_)) => {
+            Some(root_dir.join(CACHE_DIR).join(AS_OF_OUTPUT_DIR).join(interface_port.to_string()))
+        }
+        (None, None) => None,
+    }
+}
+
+/// The host part of a URL, with IPv6 addresses in brackets
+fn url_host(host: &str) -> String {
+    match host.parse::<Ipv6Addr>() {
+        Ok(_) => format!("[{}]", host),
+        Err(_) => host.to_string(),
+    }
+}
+
+/// Binds `port` on all the interfaces, returning the address that could not be bound if any
+fn bind_all(interfaces: &[IpAddr], port: u16) -> std::result::Result<Vec<TcpListener>, SocketAddr> {
+    interfaces
+        .iter()
+        .map(|ip| {
+            let address = SocketAddr::new(*ip, port);
+            TcpListener::bind(address).map_err(|_| address)
+        })
+        .collect()
+}
+
+/// Binds the first port after the well-known ones available on all the interfaces, apart from
+/// the one of the web server, for the livereload websocket
+fn reserve_livereload_port(interfaces: &[IpAddr], port_to_avoid: u16) -> Result<Vec<TcpListener>> {
+    (1024..9000)
+        .filter(|port| *port != port_to_avoid)
+        .find_map(|port| bind_all(interfaces, port).ok())
+        .ok_or_else(|| "No port available for the livereload websocket.".into())
 }
 
 #[allow(clippy::too_many_arguments)]
 fn create_new_site(
     root_dir: &Path,
-    interface: &str,
     interface_port: u16,
     output_dir: Option<&Path>,
     base_url: &str,
     config_file: &Path,
     include_drafts: bool,
-    ws_port: Option<u16>,
-) -> Result<(Site, String)> {
+    as_of: Option<&str>,
+    only: &[&str],
+    ws_port: u16,
+    https: bool,
+) -> Result<Site> {
     SITE_CONTENT.write().unwrap().clear();
+    SITE_REDIRECTS.write().unwrap().clear();
 
     let mut site = Site::new(root_dir, config_file)?;
 
-    let base_address = format!("{}:{}", base_url, interface_port);
-    let address = format!("{}:{}", interface, interface_port);
+    let base_address = format!("{}:{}", url_host(base_url), interface_port);
 
+    let scheme = if https { "https" } else { "http" };
     let base_url = if site.config.base_url.ends_with('/') {
-        format!("http://{}/", base_address)
+        format!("{}://{}/", scheme, base_address)
     } else {
-        format!("http://{}", base_address)
+        format!("{}://{}", scheme, base_address)
     };
 
     site.enable_serve_mode();
     site.set_base_url(base_url);
-    if let Some(output_dir) = output_dir {
-        site.set_output_path(output_dir);
+    if let Some(output_path) = output_path(root_dir, output_dir, as_of, interface_port) {
+        site.set_output_path(output_path);
     }
     if include_drafts {
         site.include_drafts();
     }
-    site.load()?;
-    if let Some(p) = ws_port {
-        site.enable_live_reload_with_port(p);
-    } else {
-        site.enable_live_reload(interface_port);
+    if let Some(as_of) = as_of {
+        site.set_as_of(as_of)?;
+        console::warn(&format!("Previewing the site as of {}", as_of));
     }
+    if !only.is_empty() {
+        site.set_only_paths(only)?;
+        console::warn(&format!("Partial build: only loading {}", only.join(", ")));
+    }
+    site.load()?;
+    site.enable_live_reload_with_port(ws_port);
     console::notify_site_size(&site);
     console::warn_about_ignored_pages(&site);
-    site.build()?;
-    Ok((site, address))
+    Ok(site)
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn serve(
     root_dir: &Path,
-    interface: &str,
+    interfaces: &[&str],
     interface_port: u16,
     output_dir: Option<&Path>,
-    base_url: &str,
+    base_url: Option<&str>,
     config_file: &Path,
     open: bool,
     include_drafts: bool,
+    as_of: Option<&str>,
     fast_rebuild: bool,
+    only: &[&str],
+    https: bool,
+    cert_and_key: Option<(&Path, &Path)>,
+    keep_output: bool,
+    access_log: bool,
 ) -> Result<()> {
+    let interfaces = parse_interfaces(interfaces)?;
+    let base_url = base_url.map_or_else(|| default_base_url(&interfaces), |b| b.to_string());
+    let base_url = base_url.as_str();
+
+    // Stop right there if we can't bind to the addresses
+    let listeners = bind_all(&interfaces, interface_port)
+        .map_err(|address| format!("Cannot start server on address {}.", address))?;
+    let addresses: Vec<SocketAddr> =
+        interfaces.iter().map(|ip| SocketAddr::new(*ip, interface_port)).collect();
+
+    // The livereload port is written in the pages so it is reserved before building the site,
+    // otherwise several `zola serve` started at the same time could pick the same one
+    let ws_listeners = reserve_livereload_port(&interfaces, interface_port)?;
+    let ws_port = ws_listeners[0].local_addr()?.port();
+
     let start = Instant::now();
-    let (mut site, address) = create_new_site(
+    let mut site = create_new_site(
         root_dir,
-        interface,
         interface_port,
         output_dir,
         base_url,
         config_file,
         include_drafts,
-        None,
+        as_of,
+        only,
+        ws_port,
+        https,
     )?;
+    // Only what serve writes to the output directory is deleted when it stops
+    let output_snapshot = OutputSnapshot::take(&site.output_path);
+    site.build()?;
+    BUILD_STATUS.write().unwrap().finish(&SITE_CONTENT.read().unwrap(), start.elapsed());
     console::report_elapsed_time(start);
 
-    // Stop right there if we can't bind to the address
-    let bind_address: SocketAddrV4 = match address.parse() {
-        Ok(a) => a,
-        Err(_) => return Err(format!("Invalid address: {}.", address).into()),
+    let tls = if https {
+        let hosts = tls_hosts(&interfaces, base_url);
+        Some(tls_acceptor(cert_and_key, &root_dir.join(CACHE_DIR), &hosts)?)
+    } else {
+        None
     };
-    if (TcpListener::bind(&bind_address)).is_err() {
-        return Err(format!("Cannot start server on address {}.", address).into());
-    }
 
     let config_path = PathBuf::from(config_file);
-    let config_path_rel = diff_paths(&config_path, &root_dir).unwrap_or(config_path.clone());
-
-    // An array of (path, WatchMode) where the path should be watched for changes,
-    // and the WatchMode value indicates whether this file/folder must exist for
-    // zola serve to operate
-    let watch_this = vec![
-        (config_path_rel.to_str().unwrap_or("config.toml"), WatchMode::Required),
-        ("content", WatchMode::Required),
-        ("sass", WatchMode::Condition(site.config.compile_sass)),
-        ("static", WatchMode::Optional),
-        ("templates", WatchMode::Optional),
-        ("themes", WatchMode::Condition(site.config.theme.is_some())),
-    ];
-
-    // Setup watchers
-    let (tx, rx) = channel();
-    let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
-
-    // We watch for changes on the filesystem for every entry in watch_this
-    // Will fail if either:
-    //   - the path is mandatory but does not exist (eg. config.toml)
-    //   - the path exists but has incorrect permissions
-    // watchers will contain the paths we're actually watching
-    let mut watchers = Vec::new();
-    for (entry, mode) in watch_this {
-        let watch_path = root_dir.join(entry);
-        let should_watch = match mode {
-            WatchMode::Required => true,
-            WatchMode::Optional => watch_path.exists(),
-            WatchMode::Condition(b) => b && watch_path.exists(),
-        };
-        if should_watch {
-            watcher
-                .watch(root_dir.join(entry), RecursiveMode::Recursive)
-                .map_err(|e| ZolaError::chain(format!("Can't watch `{}` for changes in folder `{}`. Does it exist, and do you have correct permissions?", entry, root_dir.display()), e))?;
-            watchers.push(entry.to_string());
-        }
-    }
+    let (rx, _watcher, watchers) = watch_site(root_dir, &config_path, &site)?;
 
-    let ws_port = site.live_reload;
-    let ws_address = format!("{}:{}", interface, ws_port.unwrap());
     let output_path = site.output_path.clone();
 
-    // output path is going to need to be moved later on, so clone it for the
-    // http closure to avoid contention.
-    let static_root = output_path.clone();
-    let broadcaster = {
-        thread::spawn(move || {
-            let addr = address.parse().unwrap();
-
+    let http_tls = tls.clone();
+    let rules = Arc::new(RwLock::new(site.response_rules()?));
+    let state = ServerState {
+        root: output_path.clone(),
+        compressor: Compressor::new(&site.config.compress_output),
+        rules: rules.clone(),
+        proxy: Proxy::new(&site.config.serve.proxy),
+        access_log,
+    };
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let http_shutdown = shutdown_rx.clone();
+    let (broadcaster, http_thread, ws_thread) = {
+        let http_thread = thread::spawn(move || {
             let rt = tokio::runtime::Builder::new_current_thread()
                 .enable_all()
                 .build()
                 .expect("Could not build tokio runtime");
 
             rt.block_on(async {
-                let make_service = make_service_fn(move |_| {
-                    let static_root = static_root.clone();
-
-                    async {
-                        Ok::<_, hyper::Error>(service_fn(move |req| {
-                            handle_request(req, static_root.clone())
-                        }))
-                    }
-                });
-
-                let server = Server::bind(&addr).serve(make_service);
-
-                println!("Web server is available at http://{}\n", &address);
+                let scheme = if http_tls.is_some() { "https" } else { "http" };
+                for address in &addresses {
+                    println!("Web server is available at {}://{}", scheme, address);
+                }
+                println!();
                 if open {
-                    if let Err(err) = open::that(format!("http://{}", &address)) {
+                    if let Err(err) = open::that(format!("{}://{}", scheme, &addresses[0])) {
                         eprintln!("Failed to open URL in your browser: {}", err);
                     }
                 }
 
-                server.await.expect("Could not start web server");
+                let mut servers = Vec::new();
+                for listener in listeners {
+                    let state = state.clone();
+                    let shutdown = http_shutdown.clone();
+                    servers.push(tokio::spawn(match http_tls.clone() {
+                        Some(acceptor) => {
+                            Box::pin(serve_https(listener, acceptor, state, shutdown))
+                                as Pin<Box<dyn Future<Output = ()> + Send>>
+                        }
+                        None => Box::pin(serve_http(listener, state, shutdown)),
+                    }));
+                }
+                for server in servers {
+                    let _ = server.await;
+                }
             });
         });
 
@@ -391,7 +890,7 @@ pub fn serve(
         let ws_server = WebSocket::new(|output: Sender| {
             move |msg: Message| {
                 if msg.into_text().unwrap().contains("\"hello\"") {
-                    return output.send(Message::text(
+                    output.send(Message::text(
                         r#"
                         {
                             "command": "hello",
@@ -399,7 +898,11 @@ pub fn serve(
                             "serverName": "Zola"
                         }
                     "#,
-                    ));
+                    ))?;
+                    // Pages loaded after a failed build still get to show the error
+                    if let Some(ref error) = *BUILD_ERROR.lock().unwrap() {
+                        return output.send(Message::text(error.as_str()));
+                    }
                 }
                 Ok(())
             }
@@ -408,27 +911,48 @@ pub fn serve(
 
         let broadcaster = ws_server.broadcaster();
 
+        // The websocket server only listens locally and doesn't do TLS: the connections made to
+        // the livereload port of the interfaces are forwarded to it, decrypted if needed
         let ws_server = ws_server
-            .bind(&*ws_address)
-            .map_err(|_| format!("Cannot bind to address {} for the websocket server. Maybe the port is already in use?", &ws_address))?;
-
+            .bind("127.0.0.1:0")
+            .map_err(|e| errors::Error::chain("Cannot start the websocket server", e))?;
+        let target = ws_server.local_addr()?;
         thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Could not build tokio runtime");
+            rt.block_on(async move {
+                let mut forwarders = Vec::new();
+                for listener in ws_listeners {
+                    let forwarder =
+                        forward_connections(listener, target, tls.clone(), shutdown_rx.clone());
+                    forwarders.push(tokio::spawn(forwarder));
+                }
+                for forwarder in forwarders {
+                    let _ = forwarder.await;
+                }
+            });
+        });
+
+        let ws_thread = thread::spawn(move || {
             ws_server.run().unwrap();
         });
 
-        broadcaster
+        (broadcaster, http_thread, ws_thread)
     };
 
     println!("Listening for changes in {}{{{}}}", root_dir.display(), watchers.join(", "));
 
     println!("Press Ctrl+C to stop\n");
-    // Delete the output folder on ctrl+C
+    // Stop on ctrl+C once the rebuild in progress, if any, is done. Pressing it again stops
+    // right away.
+    let stopping = Arc::new(AtomicBool::new(false));
+    let ctrlc_stopping = stopping.clone();
     ctrlc::set_handler(move || {
-        match remove_dir_all(&output_path) {
-            Ok(()) => (),
-            Err(e) => println!("Errored while deleting output folder: {}", e),
+        if ctrlc_stopping.swap(true, Ordering::SeqCst) {
+            ::std::process::exit(1);
         }
-        ::std::process::exit(0);
     })
     .expect("Error setting Ctrl-C handler");
 
@@ -441,20 +965,41 @@ pub fn serve(
             format!("-> Sass file changed {}", path.display())
         };
         console::info(&msg);
-        rebuild_done_handling(
-            &broadcaster,
-            compile_sass(&site.base_path, &site.output_path),
-            &partial_path.to_string_lossy(),
-        );
+        match compile_sass(&site.base_path, &site.output_path) {
+            // Livereload swaps the stylesheets of the page without reloading it when given
+            // their path
+            Ok(compiled) if !compiled.is_empty() => {
+                for css in compiled {
+                    let url_path = url_path(css.strip_prefix(&site.output_path).unwrap());
+                    BUILD_STATUS.write().unwrap().add_changed(url_path.clone());
+                    rebuild_done_handling(&broadcaster, Ok(()), &url_path);
+                }
+            }
+            res => rebuild_done_handling(
+                &broadcaster,
+                res.map(|_| ()),
+                &partial_path.to_string_lossy(),
+            ),
+        }
     };
 
     let reload_templates = |site: &mut Site, path: &Path| {
-        rebuild_done_handling(&broadcaster, site.reload_templates(), &path.to_string_lossy());
+        rebuild_done_handling(&broadcaster, site.reload_templates(path), &path.to_string_lossy());
+    };
+
+    let reload_rules = |site: &Site| -> Result<()> {
+        *rules.write().unwrap() = site.response_rules()?;
+        Ok(())
     };
 
     let copy_static = |site: &Site, path: &Path, partial_path: &Path| {
-        // Do nothing if the file/dir was deleted
+        let is_rules_file = partial_path == Path::new("/static").join(HEADERS_FILENAME)
+            || partial_path == Path::new("/static").join(REDIRECTS_FILENAME);
+        // Do nothing if the file/dir was deleted, apart from forgetting its rules
         if !path.exists() {
+            if is_rules_file {
+                rebuild_done_handling(&broadcaster, reload_rules(site), "/x.js");
+            }
             return;
         }
 
@@ -468,40 +1013,54 @@ pub fn serve(
         if path.is_dir() {
             rebuild_done_handling(
                 &broadcaster,
-                site.copy_static_directories(),
+                site.copy_static_directories().and_then(|_| reload_rules(site)),
                 &path.to_string_lossy(),
             );
+        } else if is_rules_file {
+            rebuild_done_handling(
+                &broadcaster,
+                copy_file(path, &site.output_path, &site.static_path, site.config.hard_link_static)
+                    .and_then(|_| reload_rules(site)),
+                &partial_path.to_string_lossy(),
+            );
         } else {
+            // Stylesheets and images are swapped in the page if livereload gets their URL
+            let url_path = url_path(partial_path.strip_prefix("/static").unwrap_or(partial_path));
+            BUILD_STATUS.write().unwrap().add_changed(url_path.clone());
             rebuild_done_handling(
                 &broadcaster,
                 copy_file(path, &site.output_path, &site.static_path, site.config.hard_link_static),
-                &partial_path.to_string_lossy(),
+                &url_path,
             );
         }
     };
 
     let recreate_site = || match create_new_site(
         root_dir,
-        interface,
         interface_port,
         output_dir,
         base_url,
         config_file,
         include_drafts,
+        as_of,
+        only,
         ws_port,
-    ) {
-        Ok((s, _)) => {
-            rebuild_done_handling(&broadcaster, Ok(()), "/x.js");
+        https,
+    )
+    .and_then(|s| s.build().map(|_| s))
+    {
+        Ok(s) => {
+            rebuild_done_handling(&broadcaster, reload_rules(&s), "/x.js");
             Some(s)
         }
         Err(e) => {
-            console::unravel_errors("Failed to build the site", &e);
+            rebuild_done_handling(&broadcaster, Err(e), "");
             None
         }
     };
 
-    loop {
-        match rx.recv() {
+    while !stopping.load(Ordering::SeqCst) {
+        match rx.recv_timeout(Duration::from_millis(100)) {
             Ok(event) => {
                 let can_do_fast_reload = !matches!(event, Remove(_));
 
@@ -509,16 +1068,7 @@ pub fn serve(
                     // Intellij does weird things on edit, chmod is there to count those changes
                     // https://github.com/passcod/notify/issues/150#issuecomment-494912080
                     Rename(_, path) | Create(path) | Write(path) | Remove(path) | Chmod(path) => {
-                        if is_ignored_file(&site.config.ignored_content_globset, &path) {
-                            continue;
-                        }
-
-                        if is_temp_file(&path) {
-                            continue;
-                        }
-
-                        // We only care about changes in non-empty folders
-                        if path.is_dir() && is_folder_empty(&path) {
+                        if !is_relevant_change(&site.config.ignored_content_globset, &path) {
                             continue;
                         }
                         println!(
@@ -568,7 +1118,7 @@ pub fn serve(
                                     site = s;
                                 }
                             }
-                            (ChangeKind::Templates, partial_path) => {
+                            (ChangeKind::Templates, _) => {
                                 let msg = if path.is_dir() {
                                     format!(
                                         "-> Directory in `templates` folder changed {}",
@@ -578,17 +1128,8 @@ pub fn serve(
                                     format!("-> Template changed {}", path.display())
                                 };
                                 console::info(&msg);
-
-                                // A shortcode changed, we need to rebuild everything
-                                if partial_path.starts_with("/templates/shortcodes") {
-                                    if let Some(s) = recreate_site() {
-                                        site = s;
-                                    }
-                                } else {
-                                    println!("Reloading only template");
-                                    // A normal template changed, no need to re-render Markdown.
-                                    reload_templates(&mut site, &path)
-                                }
+                                // Only what uses that template is rendered again
+                                reload_templates(&mut site, &path)
                             }
                             (ChangeKind::StaticFiles, p) => copy_static(&site, &path, &p),
                             (ChangeKind::Sass, p) => reload_sass(&site, &path, &p),
@@ -607,175 +1148,176 @@ pub fn serve(
                                 }
                             }
                         };
+                        BUILD_STATUS
+                            .write()
+                            .unwrap()
+                            .finish(&SITE_CONTENT.read().unwrap(), start.elapsed());
                         console::report_elapsed_time(start);
                     }
                     _ => {}
                 }
             }
-            Err(e) => console::error(&format!("Watch error: {:?}", e)),
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => {
+                console::error("Watch error: the file watcher stopped");
+                break;
+            }
         };
     }
-}
 
-fn is_ignored_file(ignored_content_globset: &Option<GlobSet>, path: &Path) -> bool {
-    match ignored_content_globset {
-        Some(gs) => gs.is_match(path),
-        None => false,
-    }
-}
-
-/// Returns whether the path we received corresponds to a temp file created
-/// by an editor or the OS
-fn is_temp_file(path: &Path) -> bool {
-    let ext = path.extension();
-    match ext {
-        Some(ex) => match ex.to_str().unwrap() {
-            "swp" | "swx" | "tmp" | ".DS_STORE" => true,
-            // jetbrains IDE
-            x if x.ends_with("jb_old___") => true,
-            x if x.ends_with("jb_tmp___") => true,
-            x if x.ends_with("jb_bak___") => true,
-            // vim & jetbrains
-            x if x.ends_with('~') => true,
-            _ => {
-                if let Some(filename) = path.file_stem() {
-                    // emacs
-                    let name = filename.to_str().unwrap();
-                    name.starts_with('#') || name.starts_with(".#")
-                } else {
-                    false
-                }
-            }
-        },
-        None => true,
-    }
-}
-
-/// Detect what changed from the given path so we have an idea what needs
-/// to be reloaded
-fn detect_change_kind(pwd: &Path, path: &Path, config_path: &Path) -> (ChangeKind, PathBuf) {
-    let mut partial_path = PathBuf::from("/");
-    partial_path.push(path.strip_prefix(pwd).unwrap_or(path));
-
-    let change_kind = if partial_path.starts_with("/templates") {
-        ChangeKind::Templates
-    } else if partial_path.starts_with("/themes") {
-        ChangeKind::Themes
-    } else if partial_path.starts_with("/content") {
-        ChangeKind::Content
-    } else if partial_path.starts_with("/static") {
-        ChangeKind::StaticFiles
-    } else if partial_path.starts_with("/sass") {
-        ChangeKind::Sass
-    } else if path == config_path {
-        ChangeKind::Config
+    println!("Stopping the server...");
+    let _ = shutdown_tx.send(true);
+    let _ = broadcaster.shutdown();
+    let _ = http_thread.join();
+    let _ = ws_thread.join();
+
+    if keep_output {
+        console::info(&format!("Keeping the output in {}", output_path.display()));
+        Ok(())
     } else {
-        unreachable!("Got a change in an unexpected path: {}", partial_path.display());
+        output_snapshot.remove_created()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, write};
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::{
+        default_base_url, error_location, output_path, parse_interfaces, tls_hosts, url_host,
+        url_path,
     };
+    use crate::cmd::output::OutputSnapshot;
 
-    (change_kind, partial_path)
-}
+    fn location(chain: &[&str]) -> (Option<String>, Option<usize>) {
+        error_location(&chain.iter().map(|m| m.to_string()).collect::<Vec<_>>())
+    }
 
-/// Check if the directory at path contains any file
-fn is_folder_empty(dir: &Path) -> bool {
-    // Can panic if we don't have the rights I guess?
+    #[test]
+    fn can_get_url_path_of_output_files() {
+        assert_eq!(url_path(Path::new("site.css")), "/site.css");
+        assert_eq!(url_path(&Path::new("css").join("print.css")), "/css/print.css");
+    }
 
-    read_dir(dir).expect("Failed to read a directory to see if it was empty").next().is_none()
-}
+    #[test]
+    fn can_get_output_path() {
+        let root = Path::new("site");
+        assert_eq!(output_path(root, None, None, 1111), None);
+        assert_eq!(
+            output_path(root, Some(Path::new("www")), Some("2027-01-01"), 1111),
+            Some(Path::new("www").to_path_buf())
+        );
+        assert_eq!(
+            output_path(root, None, Some("2027-01-01"), 1111),
+            Some(root.join(".zola-cache").join("as-of").join("1111"))
+        );
+    }
 
-#[cfg(test)]
-mod tests {
-    use std::path::{Path, PathBuf};
+    #[test]
+    fn previews_at_the_same_time_keep_the_files_of_each_other() {
+        let tmp_dir = tempdir().unwrap();
+        let first = output_path(tmp_dir.path(), None, Some("2026-12-01"), 1111).unwrap();
+        let second = output_path(tmp_dir.path(), None, Some("2027-01-01"), 1112).unwrap();
+
+        let first_snapshot = OutputSnapshot::take(&first);
+        create_dir_all(&first).unwrap();
+        write(first.join("site.css"), "css").unwrap();
+        let second_snapshot = OutputSnapshot::take(&second);
+        create_dir_all(&second).unwrap();
+        write(second.join("site.css"), "css").unwrap();
+
+        first_snapshot.remove_created().unwrap();
+        assert!(!first.exists());
+        assert!(second.join("site.css").exists());
+        second_snapshot.remove_created().unwrap();
+        assert!(!second.exists());
+    }
 
-    use super::{detect_change_kind, is_temp_file, ChangeKind};
+    fn ips(interfaces: &[&str]) -> Vec<IpAddr> {
+        parse_interfaces(interfaces).unwrap()
+    }
 
     #[test]
-    fn can_recognize_temp_files() {
-        let test_cases = vec![
-            Path::new("hello.swp"),
-            Path::new("hello.swx"),
-            Path::new(".DS_STORE"),
-            Path::new("hello.tmp"),
-            Path::new("hello.html.__jb_old___"),
-            Path::new("hello.html.__jb_tmp___"),
-            Path::new("hello.html.__jb_bak___"),
-            Path::new("hello.html~"),
-            Path::new("#hello.html"),
-        ];
+    fn can_get_hosts_of_self_signed_certificate() {
+        assert_eq!(
+            tls_hosts(&ips(&["127.0.0.1"]), "127.0.0.1"),
+            vec!["localhost", "127.0.0.1", "::1"]
+        );
+        assert_eq!(
+            tls_hosts(&ips(&["0.0.0.0"]), "192.168.1.2"),
+            vec!["localhost", "127.0.0.1", "::1", "192.168.1.2"]
+        );
+        assert_eq!(
+            tls_hosts(&ips(&["192.168.1.2"]), "dev.local"),
+            vec!["localhost", "127.0.0.1", "::1", "192.168.1.2", "dev.local"]
+        );
+        assert_eq!(
+            tls_hosts(&ips(&["::", "fd00::2"]), "[fd00::2]"),
+            vec!["localhost", "127.0.0.1", "::1", "fd00::2"]
+        );
+    }
 
-        for t in test_cases {
-            assert!(is_temp_file(t));
-        }
+    #[test]
+    fn can_parse_ipv4_and_ipv6_interfaces() {
+        assert_eq!(
+            ips(&["127.0.0.1", "::1", "[fe80::1]"]),
+            vec![
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST),
+                "fe80::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert!(parse_interfaces(&["localhost"]).is_err());
     }
 
     #[test]
-    fn can_detect_kind_of_changes() {
-        let test_cases = vec![
-            (
-                (ChangeKind::Templates, PathBuf::from("/templates/hello.html")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/templates/hello.html"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::Themes, PathBuf::from("/themes/hello.html")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/themes/hello.html"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::StaticFiles, PathBuf::from("/static/site.css")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/static/site.css"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::Content, PathBuf::from("/content/posts/hello.md")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/content/posts/hello.md"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::Sass, PathBuf::from("/sass/print.scss")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/sass/print.scss"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::Config, PathBuf::from("/config.toml")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/config.toml"),
-                Path::new("/home/vincent/site/config.toml"),
-            ),
-            (
-                (ChangeKind::Config, PathBuf::from("/config.staging.toml")),
-                Path::new("/home/vincent/site"),
-                Path::new("/home/vincent/site/config.staging.toml"),
-                Path::new("/home/vincent/site/config.staging.toml"),
-            ),
-        ];
+    fn can_get_default_base_url() {
+        assert_eq!(default_base_url(&ips(&["127.0.0.1"])), "127.0.0.1");
+        assert_eq!(default_base_url(&ips(&["::"])), "127.0.0.1");
+        assert_eq!(default_base_url(&ips(&["::1", "127.0.0.1"])), "127.0.0.1");
+        assert_eq!(default_base_url(&ips(&["::1"])), "::1");
+        assert_eq!(default_base_url(&ips(&["192.168.1.2"])), "192.168.1.2");
+    }
 
-        for (expected, pwd, path, config_filename) in test_cases {
-            assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
-        }
+    #[test]
+    fn can_put_ipv6_hosts_in_brackets() {
+        assert_eq!(url_host("127.0.0.1"), "127.0.0.1");
+        assert_eq!(url_host("::1"), "[::1]");
+        assert_eq!(url_host("[::1]"), "[::1]");
+        assert_eq!(url_host("dev.local"), "dev.local");
     }
 
     #[test]
-    #[cfg(windows)]
-    fn windows_path_handling() {
-        let expected = (ChangeKind::Templates, PathBuf::from("/templates/hello.html"));
-        let pwd = Path::new(r#"C:\\Users\johan\site"#);
-        let path = Path::new(r#"C:\\Users\johan\site\templates\hello.html"#);
-        let config_filename = Path::new(r#"C:\\Users\johan\site\config.toml"#);
-        assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
+    fn can_find_location_of_render_errors() {
+        let chain = [
+            "Failed to render section '/site/content/_index.md'",
+            "Failed to render 'index.html'",
+            "Variable `nope.foo` not found in context while rendering 'index.html'",
+        ];
+        assert_eq!(location(&chain), (Some("index.html".to_string()), None));
+    }
+
+    #[test]
+    fn can_find_location_of_parsing_errors() {
+        let chain = [
+            "Error parsing templates",
+            "\n* Failed to parse \"/site/templates/page.html\"\n  --> 11:7\n   |\n11 | {% if %}",
+        ];
+        assert_eq!(location(&chain), (Some("/site/templates/page.html".to_string()), Some(11)));
+
+        let chain = [
+            "Error when parsing front matter of page `/site/content/posts/a.md`",
+            "newline in string found at line 2 column 20",
+        ];
+        assert_eq!(location(&chain), (Some("/site/content/posts/a.md".to_string()), Some(2)));
     }
 
     #[test]
-    fn relative_path() {
-        let expected = (ChangeKind::Templates, PathBuf::from("/templates/hello.html"));
-        let pwd = Path::new("/home/johan/site");
-        let path = Path::new("templates/hello.html");
-        let config_filename = Path::new("config.toml");
-        assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
+    fn has_no_location_for_other_errors() {
+        assert_eq!(location(&["Can't connect to the server"]), (None, None));
     }
 }
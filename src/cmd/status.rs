@@ -0,0 +1,148 @@
+//! The status of the builds done by `zola serve`, served as JSON at `/__zola/status` so editor
+//! integrations can poll it to know when a rebuild is done, whether it failed and what changed.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use chrono::prelude::*;
+use lazy_static::lazy_static;
+use relative_path::{RelativePath, RelativePathBuf};
+use serde_json::Value;
+
+pub const STATUS_PATH: &str = "/__zola/status";
+
+lazy_static! {
+    pub static ref BUILD_STATUS: RwLock<BuildStatus> = RwLock::new(BuildStatus::default());
+}
+
+#[derive(Debug, Default)]
+pub struct BuildStatus {
+    /// Incremented on every build, so clients can tell when there was a new one
+    builds: u64,
+    finished_at: Option<DateTime<Local>>,
+    duration: Duration,
+    /// The error of the last build, as sent to the error overlay
+    error: Option<Value>,
+    /// The URL paths of the pages and files changed by the last build
+    changed: BTreeSet<String>,
+    /// What the build in progress found so far, moved to the fields above once it is done
+    next_error: Option<Value>,
+    next_changed: BTreeSet<String>,
+    /// The hash of every page and file kept in memory at the end of the last build
+    content_hashes: HashMap<RelativePathBuf, u64>,
+}
+
+impl BuildStatus {
+    pub fn set_error(&mut self, error: Value) {
+        self.next_error = Some(error);
+    }
+
+    /// Records a file written to the output directory by the build in progress
+    pub fn add_changed(&mut self, url_path: String) {
+        self.next_changed.insert(url_path);
+    }
+
+    /// Marks the build in progress as done. What it changed in memory is found by comparing
+    /// `content` with what was there at the end of the previous build.
+    pub fn finish(&mut self, content: &HashMap<RelativePathBuf, String>, duration: Duration) {
+        let hashes: HashMap<RelativePathBuf, u64> =
+            content.iter().map(|(path, content)| (path.clone(), hash(content))).collect();
+        for (path, hash) in &hashes {
+            if self.content_hashes.get(path) != Some(hash) {
+                self.next_changed.insert(content_url(path));
+            }
+        }
+        for path in self.content_hashes.keys().filter(|path| !hashes.contains_key(*path)) {
+            self.next_changed.insert(content_url(path));
+        }
+
+        self.builds += 1;
+        self.finished_at = Some(Local::now());
+        self.duration = duration;
+        self.error = self.next_error.take();
+        self.changed = std::mem::take(&mut self.next_changed);
+        self.content_hashes = hashes;
+    }
+
+    pub fn to_json(&self) -> Value {
+        serde_json::json!({
+            "builds": self.builds,
+            "finished_at": self.finished_at.map(|d| d.to_rfc3339()),
+            "duration_ms": self.duration.as_millis() as u64,
+            "ok": self.error.is_none(),
+            "error": self.error,
+            "changed": self.changed,
+        })
+    }
+}
+
+fn hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The URL path of the in-memory content at `path`: pages and sections are stored without their
+/// `index.html`, with or without a trailing slash
+fn content_url(path: &RelativePath) -> String {
+    let path = path.as_str().trim_end_matches('/');
+    match RelativePath::new(path).extension() {
+        Some(_) => format!("/{}", path),
+        None if path.is_empty() => "/".to_string(),
+        None => format!("/{}/", path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content(pages: &[(&str, &str)]) -> HashMap<RelativePathBuf, String> {
+        pages
+            .iter()
+            .map(|(path, content)| (RelativePathBuf::from(*path), content.to_string()))
+            .collect()
+    }
+
+    fn changed(status: &BuildStatus) -> Vec<&str> {
+        status.changed.iter().map(|s| s.as_str()).collect()
+    }
+
+    #[test]
+    fn can_find_what_a_build_changed() {
+        let mut status = BuildStatus::default();
+        status
+            .finish(&content(&[("", "home"), ("posts/hello", "hello")]), Duration::from_millis(5));
+        assert_eq!(changed(&status), vec!["/", "/posts/hello/"]);
+
+        status.add_changed("/site.css".to_string());
+        status.finish(
+            &content(&[("", "home"), ("posts/hello", "hello!"), ("sitemap.xml", "<urlset/>")]),
+            Duration::from_millis(2),
+        );
+        assert_eq!(changed(&status), vec!["/posts/hello/", "/site.css", "/sitemap.xml"]);
+
+        status.finish(&content(&[("", "home"), ("fixed/", "fixed")]), Duration::from_millis(1));
+        assert_eq!(changed(&status), vec!["/fixed/", "/posts/hello/", "/sitemap.xml"]);
+
+        let json = status.to_json();
+        assert_eq!(json["builds"], 3);
+        assert_eq!(json["duration_ms"], 1);
+        assert_eq!(json["ok"], true);
+    }
+
+    #[test]
+    fn keeps_error_until_next_build() {
+        let mut status = BuildStatus::default();
+        status.set_error(serde_json::json!({"message": "Failed to build the site"}));
+        status.finish(&content(&[]), Duration::from_millis(1));
+        let json = status.to_json();
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["error"]["message"], "Failed to build the site");
+
+        status.finish(&content(&[]), Duration::from_millis(1));
+        assert_eq!(status.to_json()["error"], Value::Null);
+    }
+}
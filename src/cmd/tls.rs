@@ -0,0 +1,123 @@
+//! TLS for `zola serve --https`, needed by the browser APIs only available in secure contexts
+//! when the site is opened from another device of the network.
+use std::io::BufReader;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+
+use rcgen::{Certificate, CertificateParams, DistinguishedName, SanType};
+use tokio_rustls::rustls::{self, PrivateKey, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use errors::{bail, Error, Result};
+use utils::fs::{create_file, ensure_directory_exists, read_file};
+
+/// Where the self-signed certificate is kept, relative to the cache directory
+const SELF_SIGNED_DIR: &str = "tls";
+const CERT_FILENAME: &str = "cert.pem";
+const KEY_FILENAME: &str = "key.pem";
+/// The host names the self-signed certificate was made for, one per line
+const HOSTS_FILENAME: &str = "hosts";
+
+/// Creates the acceptor for the TLS connections from the certificate and private key files if
+/// given, or from a self-signed certificate for `hosts` otherwise.
+/// The self-signed certificate is stored in `cache_path` so browsers only need to be told to
+/// trust it once, and created again if the hosts change.
+pub fn tls_acceptor(
+    cert_and_key: Option<(&Path, &Path)>,
+    cache_path: &Path,
+    hosts: &[String],
+) -> Result<TlsAcceptor> {
+    let (cert, key) = match cert_and_key {
+        Some((cert, key)) => (read_file(cert)?, read_file(key)?),
+        None => self_signed_certificate(&cache_path.join(SELF_SIGNED_DIR), hosts)?,
+    };
+
+    let certs = rustls_pemfile::certs(&mut cert.as_bytes())
+        .map_err(|e| Error::chain("Failed to read the TLS certificate", e))?;
+    if certs.is_empty() {
+        bail!("No certificate found in the TLS certificate file");
+    }
+    let key = match rustls_pemfile::read_all(&mut BufReader::new(key.as_bytes()))
+        .map_err(|e| Error::chain("Failed to read the TLS private key", e))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key) | rustls_pemfile::Item::RSAKey(key) => Some(key),
+            _ => None,
+        }) {
+        Some(key) => key,
+        None => bail!("No private key found in the TLS private key file"),
+    };
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs.into_iter().map(rustls::Certificate).collect(), PrivateKey(key))
+        .map_err(|e| Error::chain("Invalid TLS certificate or private key", e))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Returns the PEM encoded certificate and private key stored in `path`, creating them if they
+/// don't exist or are not for `hosts`
+fn self_signed_certificate(path: &Path, hosts: &[String]) -> Result<(String, String)> {
+    let cert_path = path.join(CERT_FILENAME);
+    let key_path = path.join(KEY_FILENAME);
+    let hosts_path = path.join(HOSTS_FILENAME);
+    let hosts_content = hosts.join("\n");
+
+    if read_file(&hosts_path).ok().as_deref() == Some(&hosts_content) {
+        if let (Ok(cert), Ok(key)) = (read_file(&cert_path), read_file(&key_path)) {
+            return Ok((cert, key));
+        }
+    }
+
+    let mut params = CertificateParams::new(Vec::new());
+    let mut name = DistinguishedName::new();
+    name.push(rcgen::DnType::CommonName, "Zola development server");
+    params.distinguished_name = name;
+    params.subject_alt_names = hosts
+        .iter()
+        .map(|host| match host.parse::<IpAddr>() {
+            Ok(ip) => SanType::IpAddress(ip),
+            Err(_) => SanType::DnsName(host.clone()),
+        })
+        .collect();
+    let certificate = Certificate::from_params(params)
+        .map_err(|e| Error::chain("Failed to create a self-signed certificate", e))?;
+    let cert = certificate
+        .serialize_pem()
+        .map_err(|e| Error::chain("Failed to create a self-signed certificate", e))?;
+    let key = certificate.serialize_private_key_pem();
+
+    ensure_directory_exists(path)?;
+    create_file(&cert_path, &cert)?;
+    create_file(&key_path, &key)?;
+    create_file(&hosts_path, &hosts_content)?;
+    Ok((cert, key))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn can_create_and_reuse_self_signed_certificate() {
+        let tmp_dir = tempdir().unwrap();
+        let path = tmp_dir.path().join(SELF_SIGNED_DIR);
+        let hosts = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+
+        let (cert, key) = self_signed_certificate(&path, &hosts).unwrap();
+        assert!(cert.starts_with("-----BEGIN CERTIFICATE-----"));
+        assert!(key.contains("PRIVATE KEY"));
+        assert_eq!(self_signed_certificate(&path, &hosts).unwrap(), (cert.clone(), key));
+
+        // Another host needs another certificate
+        let hosts = vec!["localhost".to_string(), "192.168.1.2".to_string()];
+        assert_ne!(self_signed_certificate(&path, &hosts).unwrap().0, cert);
+
+        assert!(tls_acceptor(None, tmp_dir.path(), &hosts).is_ok());
+    }
+}
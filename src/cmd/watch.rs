@@ -0,0 +1,252 @@
+//! The file watching shared by `zola serve` and `zola build --watch`
+use std::fs::read_dir;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use globset::GlobSet;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use pathdiff::diff_paths;
+
+use errors::{Error as ZolaError, Result};
+use site::Site;
+
+#[derive(Debug, PartialEq)]
+pub enum ChangeKind {
+    Content,
+    Templates,
+    Themes,
+    StaticFiles,
+    Sass,
+    Config,
+}
+
+#[derive(Debug, PartialEq)]
+enum WatchMode {
+    Required,
+    Optional,
+    Condition(bool),
+}
+
+/// Starts watching the parts of the site at `root_dir` that can affect the build.
+/// Returns the receiving end of the events, the watcher, which stops watching when dropped,
+/// and the list of the paths being watched.
+pub fn watch_site(
+    root_dir: &Path,
+    config_path: &Path,
+    site: &Site,
+) -> Result<(Receiver<DebouncedEvent>, RecommendedWatcher, Vec<String>)> {
+    // An array of (path, WatchMode) where the path should be watched for changes,
+    // and the WatchMode value indicates whether this file/folder must exist for
+    // zola to operate
+    let config_path_rel =
+        diff_paths(config_path, root_dir).unwrap_or_else(|| config_path.to_path_buf());
+    let watch_this = vec![
+        (config_path_rel.to_str().unwrap_or("config.toml"), WatchMode::Required),
+        ("content", WatchMode::Required),
+        ("sass", WatchMode::Condition(site.config.compile_sass)),
+        ("static", WatchMode::Optional),
+        ("templates", WatchMode::Optional),
+        ("themes", WatchMode::Condition(site.config.theme.is_some())),
+    ];
+
+    // Setup watchers
+    let (tx, rx) = channel();
+    let mut watcher = watcher(tx, Duration::from_secs(1)).unwrap();
+
+    // We watch for changes on the filesystem for every entry in watch_this
+    // Will fail if either:
+    //   - the path is mandatory but does not exist (eg. config.toml)
+    //   - the path exists but has incorrect permissions
+    // watchers will contain the paths we're actually watching
+    let mut watchers = Vec::new();
+    for (entry, mode) in watch_this {
+        let watch_path = root_dir.join(entry);
+        let should_watch = match mode {
+            WatchMode::Required => true,
+            WatchMode::Optional => watch_path.exists(),
+            WatchMode::Condition(b) => b && watch_path.exists(),
+        };
+        if should_watch {
+            watcher
+                .watch(root_dir.join(entry), RecursiveMode::Recursive)
+                .map_err(|e| ZolaError::chain(format!("Can't watch `{}` for changes in folder `{}`. Does it exist, and do you have correct permissions?", entry, root_dir.display()), e))?;
+            watchers.push(entry.to_string());
+        }
+    }
+
+    Ok((rx, watcher, watchers))
+}
+
+/// Whether a change to that path should be acted upon: it isn't ignored in the config,
+/// isn't a temp file from an editor and isn't an empty folder
+pub fn is_relevant_change(ignored_content_globset: &Option<GlobSet>, path: &Path) -> bool {
+    if is_ignored_file(ignored_content_globset, path) || is_temp_file(path) {
+        return false;
+    }
+    // We only care about changes in non-empty folders
+    !(path.is_dir() && is_folder_empty(path))
+}
+
+fn is_ignored_file(ignored_content_globset: &Option<GlobSet>, path: &Path) -> bool {
+    match ignored_content_globset {
+        Some(gs) => gs.is_match(path),
+        None => false,
+    }
+}
+
+/// Returns whether the path we received corresponds to a temp file created
+/// by an editor or the OS
+fn is_temp_file(path: &Path) -> bool {
+    let ext = path.extension();
+    match ext {
+        Some(ex) => match ex.to_str().unwrap() {
+            "swp" | "swx" | "tmp" | ".DS_STORE" => true,
+            // jetbrains IDE
+            x if x.ends_with("jb_old___") => true,
+            x if x.ends_with("jb_tmp___") => true,
+            x if x.ends_with("jb_bak___") => true,
+            // vim & jetbrains
+            x if x.ends_with('~') => true,
+            _ => {
+                if let Some(filename) = path.file_stem() {
+                    // emacs
+                    let name = filename.to_str().unwrap();
+                    name.starts_with('#') || name.starts_with(".#")
+                } else {
+                    false
+                }
+            }
+        },
+        None => true,
+    }
+}
+
+/// Detect what changed from the given path so we have an idea what needs
+/// to be reloaded
+pub fn detect_change_kind(pwd: &Path, path: &Path, config_path: &Path) -> (ChangeKind, PathBuf) {
+    let mut partial_path = PathBuf::from("/");
+    partial_path.push(path.strip_prefix(pwd).unwrap_or(path));
+
+    let change_kind = if partial_path.starts_with("/templates") {
+        ChangeKind::Templates
+    } else if partial_path.starts_with("/themes") {
+        ChangeKind::Themes
+    } else if partial_path.starts_with("/content") {
+        ChangeKind::Content
+    } else if partial_path.starts_with("/static") {
+        ChangeKind::StaticFiles
+    } else if partial_path.starts_with("/sass") {
+        ChangeKind::Sass
+    } else if path == config_path {
+        ChangeKind::Config
+    } else {
+        unreachable!("Got a change in an unexpected path: {}", partial_path.display());
+    };
+
+    (change_kind, partial_path)
+}
+
+/// Check if the directory at path contains any file
+fn is_folder_empty(dir: &Path) -> bool {
+    // Can panic if we don't have the rights I guess?
+
+    read_dir(dir).expect("Failed to read a directory to see if it was empty").next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{detect_change_kind, is_temp_file, ChangeKind};
+
+    #[test]
+    fn can_recognize_temp_files() {
+        let test_cases = vec![
+            Path::new("hello.swp"),
+            Path::new("hello.swx"),
+            Path::new(".DS_STORE"),
+            Path::new("hello.tmp"),
+            Path::new("hello.html.__jb_old___"),
+            Path::new("hello.html.__jb_tmp___"),
+            Path::new("hello.html.__jb_bak___"),
+            Path::new("hello.html~"),
+            Path::new("#hello.html"),
+        ];
+
+        for t in test_cases {
+            assert!(is_temp_file(t));
+        }
+    }
+
+    #[test]
+    fn can_detect_kind_of_changes() {
+        let test_cases = vec![
+            (
+                (ChangeKind::Templates, PathBuf::from("/templates/hello.html")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/templates/hello.html"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::Themes, PathBuf::from("/themes/hello.html")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/themes/hello.html"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::StaticFiles, PathBuf::from("/static/site.css")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/static/site.css"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::Content, PathBuf::from("/content/posts/hello.md")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/content/posts/hello.md"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::Sass, PathBuf::from("/sass/print.scss")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/sass/print.scss"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::Config, PathBuf::from("/config.toml")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/config.toml"),
+                Path::new("/home/vincent/site/config.toml"),
+            ),
+            (
+                (ChangeKind::Config, PathBuf::from("/config.staging.toml")),
+                Path::new("/home/vincent/site"),
+                Path::new("/home/vincent/site/config.staging.toml"),
+                Path::new("/home/vincent/site/config.staging.toml"),
+            ),
+        ];
+
+        for (expected, pwd, path, config_filename) in test_cases {
+            assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
+        }
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn windows_path_handling() {
+        let expected = (ChangeKind::Templates, PathBuf::from("/templates/hello.html"));
+        let pwd = Path::new(r#"C:\\Users\johan\site"#);
+        let path = Path::new(r#"C:\\Users\johan\site\templates\hello.html"#);
+        let config_filename = Path::new(r#"C:\\Users\johan\site\config.toml"#);
+        assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
+    }
+
+    #[test]
+    fn relative_path() {
+        let expected = (ChangeKind::Templates, PathBuf::from("/templates/hello.html"));
+        let pwd = Path::new("/home/johan/site");
+        let path = Path::new("templates/hello.html");
+        let config_filename = Path::new("config.toml");
+        assert_eq!(expected, detect_change_kind(pwd, path, config_filename));
+    }
+}
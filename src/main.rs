@@ -46,6 +46,12 @@ fn main() {
                 matches.value_of("base_url"),
                 output_dir,
                 matches.is_present("drafts"),
+                matches.is_present("incremental"),
+                matches.value_of("report").map(Path::new),
+                matches.is_present("watch"),
+                &matches.values_of("only").map(|v| v.collect::<Vec<_>>()).unwrap_or_default(),
+                matches.is_present("reproducible"),
+                matches.is_present("sync"),
             ) {
                 Ok(()) => console::report_elapsed_time(start),
                 Err(e) => {
@@ -55,7 +61,10 @@ fn main() {
             };
         }
         ("serve", Some(matches)) => {
-            let interface = matches.value_of("interface").unwrap_or("127.0.0.1");
+            let interfaces = matches
+                .values_of("interface")
+                .map(|v| v.collect::<Vec<_>>())
+                .unwrap_or_else(|| vec!["127.0.0.1"]);
             let mut port: u16 = match matches.value_of("port").unwrap_or("1111").parse() {
                 Ok(x) => x,
                 Err(_) => {
@@ -65,7 +74,9 @@ fn main() {
             };
             let open = matches.is_present("open");
             let include_drafts = matches.is_present("drafts");
+            let as_of = matches.value_of("as_of");
             let fast = matches.is_present("fast");
+            let only = matches.values_of("only").map(|v| v.collect::<Vec<_>>()).unwrap_or_default();
 
             // Default one
             if port != 1111 && !port_is_available(port) {
@@ -82,18 +93,31 @@ fn main() {
                 }
             }
             let output_dir = matches.value_of("output_dir").map(|output_dir| Path::new(output_dir));
-            let base_url = matches.value_of("base_url").unwrap_or("127.0.0.1");
+            let base_url = matches.value_of("base_url");
+            let https = matches.is_present("https");
+            let cert_and_key = match (matches.value_of("cert"), matches.value_of("key")) {
+                (Some(cert), Some(key)) => Some((Path::new(cert), Path::new(key))),
+                _ => None,
+            };
+            let keep_output = matches.is_present("keep_output");
+            let access_log = matches.is_present("access_log");
             console::info("Building site...");
             match cmd::serve(
                 &root_dir,
-                interface,
+                &interfaces,
                 port,
                 output_dir,
                 base_url,
                 &config_file,
                 open,
                 include_drafts,
+                as_of,
                 fast,
+                &only,
+                https,
+                cert_and_key,
+                keep_output,
+                access_log,
             ) {
                 Ok(()) => (),
                 Err(e) => {